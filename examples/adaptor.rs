@@ -1,7 +1,7 @@
 use tagu::build;
 use tagu::prelude::*;
 
-fn main() -> std::fmt::Result {
+fn main() -> Result<(), tagu::Error> {
     let a = build::elem("a");
     let b = build::elem("b");
     let c = build::elem("c");
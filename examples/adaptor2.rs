@@ -1,7 +1,7 @@
 use tagu::build;
 use tagu::prelude::*;
 
-fn main() -> std::fmt::Result {
+fn main() -> Result<(), tagu::Error> {
     let all = build::elem("a").append_with(|| {
         elems!(
             build::single("test"),
@@ -1,9 +1,8 @@
-use std::fmt;
 use tagu::build;
 use tagu::prelude::*;
 use tagu::stack::ElemStack;
 
-fn func<T>(stack: ElemStack<T>) -> Result<ElemStack<T>, fmt::Error> {
+fn func<T>(stack: ElemStack<T>) -> Result<ElemStack<T>, tagu::Error> {
     let a = build::elem("a2");
     let b = build::elem("b2");
     let c = build::elem("c2");
@@ -17,7 +16,7 @@ fn func<T>(stack: ElemStack<T>) -> Result<ElemStack<T>, fmt::Error> {
     stack.pop()?.pop()?.pop()
 }
 
-fn main() -> std::fmt::Result {
+fn main() -> Result<(), tagu::Error> {
     let all = build::from_stack(|stack| func(stack.push(build::elem("ha"))?)?.pop());
 
     tagu::render(all, tagu::stdout_fmt())
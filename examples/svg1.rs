@@ -1,7 +1,8 @@
 use tagu::build;
+use tagu::ns;
 use tagu::prelude::*;
 
-fn main() -> std::fmt::Result {
+fn main() -> Result<(), tagu::Error> {
     let width = 100.0;
     let height = 100.0;
 
@@ -19,10 +20,8 @@ fn main() -> std::fmt::Result {
         .inline()
         .append(build::raw(".test{fill:none;stroke:white;stroke-width:3}"));
 
-    let svg = build::elem("svg").with(attrs!(
-        ("xmlns", "http://www.w3.org/2000/svg"),
-        ("viewBox", format_move!("0 0 {} {}", width, height))
-    ));
+    let svg =
+        build::elem_ns(ns::SVG, "svg").with(("viewBox", format_move!("0 0 {} {}", width, height)));
 
     let rows = build::from_stack(|mut f| {
         for r in (0..50).step_by(5) {
@@ -46,5 +45,7 @@ fn main() -> std::fmt::Result {
 
     let all = svg.append(style).append(rect).append(table);
 
-    tagu::render(all, tagu::stdout_fmt())
+    tagu::render::Renderer::new()
+        .with_line_width(80)
+        .render(all, tagu::stdout_fmt())
 }
@@ -1,15 +1,16 @@
 use tagu::build;
+use tagu::ns;
 use tagu::prelude::*;
 
-fn main() -> std::fmt::Result {
+fn main() -> Result<(), tagu::Error> {
     let width = 100.0;
     let height = 100.0;
 
     let all = build::from_stack(|w| {
-        let mut w = w.push(build::elem("svg").with(attrs!(
-            ("xmlns", "http://www.w3.org/2000/svg"),
-            ("viewBox", format_move!("0 0 {} {}", width, height))
-        )))?;
+        let mut w = w.push(
+            build::elem_ns(ns::SVG, "svg")
+                .with(("viewBox", format_move!("0 0 {} {}", width, height))),
+        )?;
 
         w.put(
             build::elem("style")
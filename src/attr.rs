@@ -9,7 +9,7 @@ use fmt::Write;
 /// The attribute building block trait
 ///
 pub trait Attr {
-    fn render(self, w: &mut AttrWrite) -> std::fmt::Result;
+    fn render(self, w: &mut AttrWrite) -> Result<(), Error>;
     fn chain<R: Attr>(self, other: R) -> AttrChain<Self, R>
     where
         Self: Sized,
@@ -29,7 +29,7 @@ pub trait Attr {
 }
 
 impl<A: Attr> Attr for Option<A> {
-    fn render(self, w: &mut AttrWrite) -> std::fmt::Result {
+    fn render(self, w: &mut AttrWrite) -> Result<(), Error> {
         if let Some(a) = self {
             a.render(w)?;
         }
@@ -38,11 +38,87 @@ impl<A: Attr> Attr for Option<A> {
 }
 
 impl Attr for () {
-    fn render(self, _: &mut AttrWrite) -> std::fmt::Result {
+    fn render(self, _: &mut AttrWrite) -> Result<(), Error> {
         Ok(())
     }
 }
 
+///
+/// Each attr in order.
+///
+/// ```
+/// use tagu::build;
+/// let extra = vec![("id", "a"), ("title", "b")];
+/// let k = build::single("p").with(extra);
+/// assert_eq!(tagu::render_to_string(k).unwrap(), "<p id=\"a\" title=\"b\"/>\n");
+/// ```
+///
+impl<A: Attr> Attr for Vec<A> {
+    fn render(self, w: &mut AttrWrite) -> Result<(), Error> {
+        self.into_iter().try_for_each(|a| a.render(w))
+    }
+}
+
+///
+/// Each entry in order of its key.
+///
+impl<K: fmt::Display, V: fmt::Display> Attr for std::collections::BTreeMap<K, V> {
+    fn render(self, w: &mut AttrWrite) -> Result<(), Error> {
+        self.into_iter().try_for_each(|a| a.render(w))
+    }
+}
+
+///
+/// Each entry, sorted by the text of its key so that the output does not change between runs.
+///
+/// ```
+/// use std::collections::HashMap;
+/// use tagu::build;
+/// let map = HashMap::from([("width", 5), ("height", 2), ("x", 1)]);
+/// let k = build::single("rect").with(map);
+/// assert_eq!(
+///     tagu::render_to_string(k).unwrap(),
+///     "<rect height=\"2\" width=\"5\" x=\"1\"/>\n"
+/// );
+/// ```
+///
+impl<K: fmt::Display, V: fmt::Display, S> Attr for std::collections::HashMap<K, V, S> {
+    fn render(self, w: &mut AttrWrite) -> Result<(), Error> {
+        let mut entries: Vec<_> = self.into_iter().map(|(k, v)| (k.to_string(), v)).collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        entries.into_iter().try_for_each(|a| a.render(w))
+    }
+}
+
+///
+/// The attrs of an iterator, like `(K, V)` pairs.
+///
+#[derive(Copy, Clone)]
+#[must_use]
+pub struct AttrIter<I> {
+    iter: I,
+}
+
+impl<I: IntoIterator<Item = A>, A: Attr> Attr for AttrIter<I> {
+    fn render(self, w: &mut AttrWrite) -> Result<(), Error> {
+        self.iter.into_iter().try_for_each(|a| a.render(w))
+    }
+}
+
+///
+/// Create an attr from an iterator of attrs.
+///
+/// ```
+/// use tagu::attr;
+/// use tagu::build;
+/// let k = build::single("p").with(attr::from_iter((1..3).map(|i| (format!("x{}", i), i))));
+/// assert_eq!(tagu::render_to_string(k).unwrap(), "<p x1=\"1\" x2=\"2\"/>\n");
+/// ```
+///
+pub fn from_iter<I: IntoIterator<Item = A>, A: Attr>(iter: I) -> AttrIter<I> {
+    AttrIter { iter }
+}
+
 ///
 /// Chain together two attrs
 ///
@@ -53,7 +129,7 @@ pub struct AttrChain<A, B> {
     second: B,
 }
 impl<A: Attr, B: Attr> Attr for AttrChain<A, B> {
-    fn render(self, w: &mut AttrWrite) -> std::fmt::Result {
+    fn render(self, w: &mut AttrWrite) -> Result<(), Error> {
         let AttrChain { first, second } = self;
         first.render(w)?;
         //w.writer().write_str(" ")?;
@@ -64,30 +140,781 @@ impl<A: Attr, B: Attr> Attr for AttrChain<A, B> {
 ///
 /// The attr writer passed to the closure attr
 ///
-pub struct AttrWrite<'a>(WriteWrap<'a>);
+pub struct AttrWrite<'a>(&'a mut AttrBuf, &'a mut render::State);
 impl<'a> AttrWrite<'a> {
-    pub(super) fn new(wrap: WriteWrap<'a>) -> Self {
-        AttrWrite(wrap)
+    pub(super) fn new(buf: &'a mut AttrBuf, state: &'a mut render::State) -> Self {
+        AttrWrite(buf, state)
     }
-    pub fn render<E: Attr>(&mut self, attr: E) -> fmt::Result {
+    pub fn render<E: Attr>(&mut self, attr: E) -> Result<(), Error> {
         attr.render(self)
     }
-    pub fn writer(&mut self) -> tools::EscapeGuard<WriteWrap> {
-        tools::escape_guard(self.0.borrow_mut())
+    ///
+    /// Write part of an attribute value.
+    ///
+    pub fn writer(&mut self) -> tools::Escaper<WriteWrap<'_>> {
+        self.1.escaper(WriteWrap(self.0), tools::Context::Attr)
+    }
+
+    ///
+    /// Write an attribute name, failing if it is not an xml `Name`.
+    ///
+    fn write_name<K: tools::AsName + ?Sized>(&mut self, key: &K) -> Result<(), Error> {
+        if let Some(name) = key.checked() {
+            if self.1.ascii && !name.is_ascii() {
+                return Err(Error::new(ErrorKind::Name(name.as_str().to_string())));
+            }
+            self.writer_escapable().write_str(name.as_str())?;
+        } else {
+            let mut w = tools::name_writer(WriteWrap(self.0), self.1.ascii);
+            key.write_name(&mut w)
+                .and_then(|_| w.finish())
+                .map_err(|_| w.error(|| tools::ShowName(key).to_string()))?;
+        }
+        self.0.end_name();
+        Ok(())
+    }
+
+    fn writer_escapable(&mut self) -> WriteWrap<'_> {
+        WriteWrap(self.0)
     }
 
-    fn writer_escapable(&mut self) -> WriteWrap {
-        self.0.borrow_mut()
+    ///
+    /// Start the next attribute.
+    ///
+    fn attr_sep(&mut self) -> Result<(), Error> {
+        self.0.start(Kind::Attr);
+        Ok(())
+    }
+
+    ///
+    /// Add to the classes of the element, unless they are there already.
+    ///
+    fn add_classes(&mut self, classes: &str) {
+        for class in classes.split_ascii_whitespace() {
+            self.0.reserve(Kind::Class);
+            if !self.0.classes.iter().any(|a| a == class) {
+                self.0.classes.push(class.to_string());
+            }
+        }
+    }
+
+    ///
+    /// Write the attributes that are collected from several attrs,
+    /// in the place of the first attr that added to them.
+    ///
+    pub(crate) fn finish(&mut self) -> Result<(), Error> {
+        self.0.close();
+        if self.0.has(Kind::Class) {
+            let classes = std::mem::take(&mut self.0.classes);
+            self.key_value("class", classes.join(" "))?;
+            self.0.fill(Kind::Class);
+        }
+        if self.0.has(Kind::Style) {
+            let styles = std::mem::take(&mut self.0.styles);
+            self.attr_sep()?;
+            self.write_name(&tools::Name::new("style"))?;
+            self.writer_escapable().write_str("=\"")?;
+            for (i, (property, value)) in styles.iter().enumerate() {
+                let sep = if i == 0 { "" } else { ";" };
+                let mut e = self.writer();
+                write!(e, "{}{}:{}", sep, property, value).map_err(|_| e.error())?;
+            }
+            self.writer_escapable().write_str("\"")?;
+            self.0.fill(Kind::Style);
+        }
+        Ok(())
+    }
+
+    ///
+    /// Set a css declaration of the element, replacing an earlier one of the same property.
+    ///
+    fn add_style(&mut self, property: &str, value: &str) -> Result<(), Error> {
+        if !tools::is_css_property(property) {
+            return Err(Error::new(ErrorKind::Name(property.to_string())));
+        }
+        self.0.reserve(Kind::Style);
+        let value = tools::css_value(value);
+        match self.0.styles.iter_mut().find(|(a, _)| a == property) {
+            Some((_, v)) => *v = value,
+            None => self.0.styles.push((property.to_string(), value)),
+        }
+        Ok(())
+    }
+
+    ///
+    /// Set the css declarations of a `style` attribute, like `fill: blue; width: 100%`.
+    ///
+    fn add_styles(&mut self, styles: &str) -> Result<(), Error> {
+        for declaration in tools::css_declarations(styles) {
+            if declaration.trim().is_empty() {
+                continue;
+            }
+            let Some((property, value)) = declaration.split_once(':') else {
+                return Err(Error::new(ErrorKind::Name(declaration.trim().to_string())));
+            };
+            self.add_style(property.trim(), value.trim())?;
+        }
+        Ok(())
+    }
+
+    ///
+    /// Bring a namespace into scope, declaring it if it is not already.
+    ///
+    pub(crate) fn declare(&mut self, ns: ns::Namespace) -> Result<(), Error> {
+        if !self.1.ns.bind(ns)? {
+            return Ok(());
+        }
+        self.0.start(Kind::Namespace);
+        match ns.prefix() {
+            Some(p) => self.write_name(&format_args!("xmlns:{}", p))?,
+            None => self.write_name(&tools::Name::new("xmlns"))?,
+        }
+        self.writer_escapable().write_str("=\"")?;
+        self.writer().write_str(ns.uri())?;
+        self.writer_escapable().write_str("\"")?;
+        Ok(())
+    }
+
+    fn key_value<A: tools::AsName + ?Sized, B: fmt::Display>(
+        &mut self,
+        key: &A,
+        value: B,
+    ) -> Result<(), Error> {
+        self.attr_sep()?;
+        self.write_name(key)?;
+        self.writer_escapable().write_str("=\"")?;
+        let mut e = self.writer();
+        write!(e, "{}", value).map_err(|_| e.error())?;
+        self.writer_escapable().write_str("\"")?;
+        Ok(())
     }
 }
 
-impl<A: fmt::Display, B: fmt::Display> Attr for (A, B) {
-    fn render(self, w: &mut AttrWrite) -> std::fmt::Result {
+///
+/// What to do when a start tag has the same attribute more than once.
+///
+/// ```
+/// use tagu::attr::Duplicates;
+/// use tagu::build;
+/// use tagu::render::Renderer;
+/// let k = || build::single("rect").with(("x", 0)).with(("y", 1)).with(("x", 5));
+///
+/// let mut s = String::new();
+/// Renderer::new().render(k(), &mut s).unwrap();
+/// assert_eq!(s, "<rect x=\"0\" y=\"1\" x=\"5\"/>\n");
+///
+/// let e = Renderer::new()
+///     .with_duplicates(Duplicates::Error)
+///     .render(k(), &mut String::new())
+///     .unwrap_err();
+/// assert!(matches!(e.kind(), tagu::ErrorKind::DuplicateAttr(a) if a == "x"));
+///
+/// let mut s = String::new();
+/// Renderer::new().with_duplicates(Duplicates::KeepLast).render(k(), &mut s).unwrap();
+/// assert_eq!(s, "<rect y=\"1\" x=\"5\"/>\n");
+///
+/// let mut s = String::new();
+/// Renderer::new().with_duplicates(Duplicates::KeepFirst).render(k(), &mut s).unwrap();
+/// assert_eq!(s, "<rect x=\"0\" y=\"1\"/>\n");
+///
+/// let k = build::elem("a").with(tagu::attr::AttrClosure::new(|w| {
+///     w.render(("href", "a"))?;
+///     w.render(("HREF", "b"))
+/// }));
+/// let e = Renderer::new()
+///     .with_mode(tagu::render::Mode::Html)
+///     .with_duplicates(Duplicates::Error)
+///     .render(k, &mut String::new())
+///     .unwrap_err();
+/// assert!(matches!(e.kind(), tagu::ErrorKind::DuplicateAttr(a) if a == "HREF"));
+/// ```
+///
+/// Namespace declarations are always written, as the elements inside rely on them.
+/// An attribute with the same name is the duplicate instead.
+///
+/// ```
+/// use tagu::attr::{AttrNs, Duplicates};
+/// use tagu::build;
+/// use tagu::ns::Namespace;
+/// use tagu::render::Renderer;
+/// let x = Namespace::prefixed("x", "urn:x");
+/// let k = build::single("a").with(AttrNs::new(x, "b", 1)).with(("xmlns:x", "urn:y"));
+/// let mut s = String::new();
+/// Renderer::new().with_duplicates(Duplicates::KeepLast).render(k, &mut s).unwrap();
+/// assert_eq!(s, "<a xmlns:x=\"urn:x\" x:b=\"1\"/>\n");
+/// ```
+///
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Duplicates {
+    /// Write all of them.
+    #[default]
+    Allow,
+    /// Fail the render.
+    Error,
+    /// Only write the first one.
+    KeepFirst,
+    /// Only write the last one.
+    KeepLast,
+}
+
+///
+/// The attributes of a start tag, held until all of them are known.
+///
+#[derive(Default)]
+pub(crate) struct AttrBuf {
+    data: String,
+    /// The attributes in the order they are written, each a range of `data`.
+    slots: Vec<Slot>,
+    /// The slot that writes go to.
+    open: Option<usize>,
+    /// The end of anything written before the first attribute.
+    prefix: Option<usize>,
+    classes: Vec<String>,
+    styles: Vec<(String, String)>,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Kind {
+    Attr,
+    /// A namespace declaration, which the namespace scopes rely on.
+    Namespace,
+    /// Where the classes are written once they are all known.
+    Class,
+    /// Where the css declarations are written once they are all known.
+    Style,
+}
+
+struct Slot {
+    kind: Kind,
+    start: usize,
+    /// The name is from `start` up to this.
+    name_end: usize,
+    end: usize,
+}
+
+impl AttrBuf {
+    pub(crate) fn clear(&mut self) {
+        self.data.clear();
+        self.slots.clear();
+        self.open = None;
+        self.prefix = None;
+        self.classes.clear();
+        self.styles.clear();
+    }
+
+    ///
+    /// End the slot that writes go to.
+    ///
+    fn close(&mut self) {
+        let len = self.data.len();
+        self.prefix.get_or_insert(len);
+        if let Some(i) = self.open.take() {
+            self.slots[i].end = len;
+        }
+    }
+
+    ///
+    /// Start an attribute, which writes go to from now on.
+    ///
+    fn start(&mut self, kind: Kind) {
+        self.close();
+        let len = self.data.len();
+        self.open = Some(self.slots.len());
+        self.slots.push(Slot {
+            kind,
+            start: len,
+            name_end: len,
+            end: len,
+        });
+    }
+
+    ///
+    /// Keep the place of an attribute that is written later, unless it has one already.
+    ///
+    fn reserve(&mut self, kind: Kind) {
+        if !self.has(kind) {
+            self.prefix.get_or_insert(self.data.len());
+            self.slots.push(Slot {
+                kind,
+                start: 0,
+                name_end: 0,
+                end: 0,
+            });
+        }
+    }
+
+    fn has(&self, kind: Kind) -> bool {
+        self.slots.iter().any(|a| a.kind == kind)
+    }
+
+    ///
+    /// Move the attribute that was just written to the place that was kept for it.
+    ///
+    fn fill(&mut self, kind: Kind) {
+        self.close();
+        if let Some(Slot {
+            start,
+            name_end,
+            end,
+            ..
+        }) = self.slots.pop()
+        {
+            if let Some(a) = self.slots.iter_mut().find(|a| a.kind == kind) {
+                (a.start, a.name_end, a.end) = (start, name_end, end);
+            }
+        }
+    }
+
+    ///
+    /// The name of the attribute that writes go to ends here.
+    ///
+    fn end_name(&mut self) {
+        if let Some(i) = self.open {
+            self.slots[i].name_end = self.data.len();
+        }
+    }
+
+    ///
+    /// Anything written before the first attribute.
+    ///
+    pub(crate) fn prefix(&self) -> &str {
+        &self.data[..self.prefix.unwrap_or(self.data.len())]
+    }
+
+    ///
+    /// The name and the text of each attribute, and whether it declares a namespace.
+    ///
+    pub(crate) fn attrs(&self) -> impl Iterator<Item = (&str, &str, bool)> + Clone {
+        self.slots.iter().map(|a| {
+            (
+                &self.data[a.start..a.name_end],
+                &self.data[a.start..a.end],
+                a.kind == Kind::Namespace,
+            )
+        })
+    }
+}
+
+impl fmt::Write for AttrBuf {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.data.push_str(s);
+        Ok(())
+    }
+}
+
+impl tools::Sink for AttrBuf {}
+
+///
+/// A key value attribute. The key has to be an xml `Name`, otherwise rendering fails.
+///
+/// ```
+/// use tagu::build;
+/// let e = tagu::render(build::single("a").with(("on click", "")), &mut String::new()).unwrap_err();
+/// assert!(matches!(e.kind(), tagu::ErrorKind::Name(a) if a == "on click"));
+/// assert_eq!(e.path(), ["a"]);
+///
+/// let e = tagu::render_html(build::elem("1a"), &mut String::new()).unwrap_err();
+/// assert_eq!(e.to_string(), "invalid name \"1a\"");
+/// ```
+///
+impl<A: tools::AsName, B: fmt::Display> Attr for (A, B) {
+    fn render(self, w: &mut AttrWrite) -> Result<(), Error> {
         let (first, second) = self;
-        write!(w.writer(), " {}", first)?;
-        w.writer_escapable().write_str("=\"")?;
-        write!(w.writer(), "{}", second)?;
-        w.writer_escapable().write_str("\"")
+        let html = w.1.mode == render::Mode::Html;
+        let key = tools::ShowName(&first);
+        // Merged with the other classes and declarations of the element.
+        match html::find_display(&["class", "style"], &key) {
+            Some(name) if html || key.to_string() == name => {
+                let value = second.to_string();
+                return match name {
+                    "class" => {
+                        w.add_classes(&value);
+                        Ok(())
+                    }
+                    _ => w.add_styles(&value),
+                };
+            }
+            _ => {}
+        }
+        if html {
+            if let Some(name) = html::boolean_attr(&tools::ShowName(&first)) {
+                // Written bare if the value is empty or the name itself.
+                if html::find_display(&["", name], &second).is_some() {
+                    w.attr_sep()?;
+                    return w.write_name(&first);
+                }
+            }
+        }
+        w.key_value(&first, second)
+    }
+}
+
+///
+/// A key value attribute that is left out when there is no value.
+///
+/// This can't be an impl for `(K, Option<V>)`, as that would overlap with the impl for `(A, B)`
+/// should `Option` ever implement `Display`.
+///
+/// ```
+/// use tagu::attr;
+/// use tagu::build;
+/// use tagu::prelude::*;
+/// let title: Option<&str> = None;
+/// let k = build::single("img").with(attrs!(
+///     attr::optional("src", Some("a.png")),
+///     attr::optional("title", title)
+/// ));
+/// assert_eq!(tagu::render_to_string(k).unwrap(), "<img src=\"a.png\"/>\n");
+/// ```
+///
+pub fn optional<K: tools::AsName, V: fmt::Display>(key: K, value: Option<V>) -> Option<(K, V)> {
+    value.map(|value| (key, value))
+}
+
+///
+/// A boolean attribute, which is true by being there.
+///
+/// In `Mode::Html` it is written bare, and in `Mode::Xml` with its name as its value.
+///
+/// ```
+/// use tagu::attr;
+/// use tagu::build;
+/// use tagu::prelude::*;
+/// let k = || build::single("input").with(attrs!(attr::flag("disabled"), attr::flag_if(false, "checked")));
+///
+/// let mut s = String::new();
+/// tagu::render_html(k(), &mut s).unwrap();
+/// assert_eq!(s, "<input disabled>\n");
+///
+/// assert_eq!(tagu::render_to_string(k()).unwrap(), "<input disabled=\"disabled\"/>\n");
+/// ```
+///
+#[derive(Copy, Clone)]
+#[must_use]
+pub struct Flag<K> {
+    name: K,
+    on: bool,
+}
+
+impl<K: tools::AsName> Attr for Flag<K> {
+    fn render(self, w: &mut AttrWrite) -> Result<(), Error> {
+        let Flag { name, on } = self;
+        if !on {
+            return Ok(());
+        }
+        if w.1.mode == render::Mode::Html {
+            w.attr_sep()?;
+            return w.write_name(&name);
+        }
+        w.key_value(&name, tools::ShowName(&name))
+    }
+}
+
+///
+/// A boolean attribute that is set. See `Flag`.
+///
+pub fn flag<K: tools::AsName>(name: K) -> Flag<K> {
+    Flag { name, on: true }
+}
+
+///
+/// A boolean attribute that is set if the condition holds. See `Flag`.
+///
+pub fn flag_if<K: tools::AsName>(cond: bool, name: K) -> Flag<K> {
+    Flag { name, on: cond }
+}
+
+///
+/// `data-*` attributes, see `data_attrs()`.
+///
+#[derive(Copy, Clone)]
+#[must_use]
+pub struct DataAttrs<P, I> {
+    prefix: P,
+    iter: I,
+}
+
+impl<P: fmt::Display, I: IntoIterator<Item = (K, V)>, K: fmt::Display, V: fmt::Display> Attr
+    for DataAttrs<P, I>
+{
+    fn render(self, w: &mut AttrWrite) -> Result<(), Error> {
+        let prefix = self.prefix.to_string();
+        for (key, value) in self.iter {
+            let mut name = String::from("data-");
+            if !prefix.is_empty() {
+                write!(name, "{}-", prefix)?;
+            }
+            let start = name.len();
+            write!(name, "{}", key)?;
+            let valid = name.len() > start
+                && tools::is_name(&name)
+                && !name.contains(|c: char| c.is_ascii_uppercase() || c == ':');
+            if !valid {
+                return Err(Error::new(ErrorKind::Name(name)));
+            }
+            w.key_value(&name, value)?;
+        }
+        Ok(())
+    }
+}
+
+///
+/// Create `data-*` attributes, with names made of `data-`, the prefix followed by `-`
+/// unless it is empty, and the key. The name has to be an xml `Name` without
+/// ascii upper case letters or colons, as html requires, otherwise rendering fails.
+///
+/// ```
+/// use tagu::attr;
+/// use tagu::build;
+/// let k = build::single("div").with(attr::data_attrs("user", [("id", 5), ("role", 1)]));
+/// assert_eq!(
+///     tagu::render_to_string(k).unwrap(),
+///     "<div data-user-id=\"5\" data-user-role=\"1\"/>\n"
+/// );
+///
+/// let k = build::single("div").with(attr::data_attrs("", [("userId", 5)]));
+/// let e = tagu::render_to_string(k).unwrap_err();
+/// assert!(matches!(e.kind(), tagu::ErrorKind::Name(a) if a == "data-userId"));
+///
+/// let k = build::single("div").with(attr::data_attrs("", [("a:b", 1)]));
+/// assert!(tagu::render_to_string(k).is_err());
+///
+/// let k = build::single("div").with(attr::data_attrs("x:y", [("a", 1)]));
+/// assert!(tagu::render_to_string(k).is_err());
+/// ```
+///
+pub fn data_attrs<P, I, K, V>(prefix: P, iter: I) -> DataAttrs<P, I>
+where
+    P: fmt::Display,
+    I: IntoIterator<Item = (K, V)>,
+    K: fmt::Display,
+    V: fmt::Display,
+{
+    DataAttrs { prefix, iter }
+}
+
+///
+/// An attribute in a namespace
+///
+#[derive(Copy, Clone)]
+#[must_use]
+pub struct AttrNs<K, V> {
+    ns: ns::Namespace,
+    key: K,
+    value: V,
+}
+impl<K: fmt::Display, V: fmt::Display> AttrNs<K, V> {
+    pub fn new(ns: ns::Namespace, key: K, value: V) -> Self {
+        AttrNs { ns, key, value }
+    }
+}
+impl<K: fmt::Display, V: fmt::Display> Attr for AttrNs<K, V> {
+    fn render(self, w: &mut AttrWrite) -> Result<(), Error> {
+        let AttrNs { ns, key, value } = self;
+        let Some(prefix) = ns.prefix() else {
+            return Err(Error::new(ErrorKind::Namespace));
+        };
+        w.declare(ns)?;
+        w.key_value(&format_args!("{}:{}", prefix, key), value)
+    }
+}
+
+///
+/// A `class` attribute. Classes are added one by one, conditionally or from iterators.
+///
+/// All the classes of an element end up in a single `class` attribute, without duplicates,
+/// however many `Classes` or `("class", ..)` pairs it has. It is written where the first
+/// of them with a class is, and left out if none of them has one.
+///
+/// ```
+/// use tagu::attr::Classes;
+/// use tagu::build;
+/// let active = true;
+/// let base = Classes::new().with("btn btn-primary").with_if(active, "active");
+/// let theme = Classes::from_iter(["dark", "btn"]);
+/// let k = build::single("button").with(base).with(("id", "ok")).with(theme);
+/// assert_eq!(
+///     tagu::render_to_string(k).unwrap(),
+///     "<button class=\"btn btn-primary active dark\" id=\"ok\"/>\n"
+/// );
+///
+/// let k = build::single("p").with(("id", "a")).with(("class", "x y")).with(Classes::new().with("y z"));
+/// assert_eq!(tagu::render_to_string(k).unwrap(), "<p id=\"a\" class=\"x y z\"/>\n");
+///
+/// let k = build::single("p").with(Classes::new().with(" ")).with(("class", ""));
+/// assert_eq!(tagu::render_to_string(k).unwrap(), "<p/>\n");
+/// ```
+///
+#[derive(Clone, Debug, Default)]
+#[must_use]
+pub struct Classes<'a> {
+    list: Vec<std::borrow::Cow<'a, str>>,
+}
+
+impl<'a> Classes<'a> {
+    pub fn new() -> Self {
+        Classes { list: Vec::new() }
+    }
+
+    ///
+    /// Add one or more classes, separated by whitespace.
+    ///
+    pub fn with(mut self, class: impl Into<std::borrow::Cow<'a, str>>) -> Self {
+        self.list.push(class.into());
+        self
+    }
+
+    ///
+    /// Add classes if the condition holds.
+    ///
+    pub fn with_if(self, cond: bool, class: impl Into<std::borrow::Cow<'a, str>>) -> Self {
+        if cond {
+            self.with(class)
+        } else {
+            self
+        }
+    }
+
+    ///
+    /// Add the classes of an iterator.
+    ///
+    pub fn with_all<I: IntoIterator>(mut self, iter: I) -> Self
+    where
+        I::Item: Into<std::borrow::Cow<'a, str>>,
+    {
+        self.list.extend(iter.into_iter().map(Into::into));
+        self
+    }
+}
+
+impl<'a, C: Into<std::borrow::Cow<'a, str>>> FromIterator<C> for Classes<'a> {
+    fn from_iter<I: IntoIterator<Item = C>>(iter: I) -> Self {
+        Classes::new().with_all(iter)
+    }
+}
+
+impl Attr for Classes<'_> {
+    fn render(self, w: &mut AttrWrite) -> Result<(), Error> {
+        for class in self.list.iter() {
+            w.add_classes(class);
+        }
+        Ok(())
+    }
+}
+
+///
+/// A `style` attribute, made of css declarations.
+///
+/// Values are escaped so that each stays a single declaration. All the declarations of an
+/// element end up in a single `style` attribute, where a later declaration of a property
+/// replaces an earlier one. That includes those of `("style", ..)` pairs, which are split
+/// on the `;` between declarations. It is written where the first of them with a
+/// declaration is, and left out if none of them has one.
+///
+/// ```
+/// use tagu::attr::Style;
+/// use tagu::build;
+/// let highlight = false;
+/// let base = Style::new().with("fill", "blue").with("width", format_args!("{}%", 100));
+/// let state = Style::new().with_if(highlight, "stroke", "red").with("fill", "green; x: y");
+/// let k = build::single("rect").with(base).with(state);
+/// assert_eq!(
+///     tagu::render_to_string(k).unwrap(),
+///     "<rect style=\"fill:green\\; x: y;width:100%\"/>\n"
+/// );
+///
+/// let k = build::single("rect")
+///     .with(("style", "fill: red; background: url(a;b)"))
+///     .with(("x", 0))
+///     .with(Style::new().with("fill", "blue"));
+/// assert_eq!(
+///     tagu::render_to_string(k).unwrap(),
+///     "<rect style=\"fill:blue;background:url(a\\;b)\" x=\"0\"/>\n"
+/// );
+///
+/// let k = build::single("i").with(Style::new().with("content", "\"\\f101\""));
+/// assert_eq!(
+///     tagu::render_to_string(k).unwrap(),
+///     "<i style=\"content:&quot;\\f101&quot;\"/>\n"
+/// );
+///
+/// // A string that is left open is closed, so that it can't take in the next declaration.
+/// let k = build::single("i").with(Style::new().with("content", "\"\\\"").with("color", "red"));
+/// assert_eq!(
+///     tagu::render_to_string(k).unwrap(),
+///     "<i style=\"content:&quot;\\&quot;&quot;;color:red\"/>\n"
+/// );
+///
+/// let k = build::single("rect").with(Style::new().with("fill;", "blue"));
+/// assert!(tagu::render_to_string(k).is_err());
+/// ```
+///
+#[derive(Default)]
+#[must_use]
+pub struct Style {
+    /// The declarations back to back, each a property followed by its value.
+    text: String,
+    /// Where the property and the value of each declaration end in `text`.
+    ends: Vec<(usize, usize)>,
+    /// Formatting a value failed.
+    failed: bool,
+}
+
+impl Style {
+    pub fn new() -> Self {
+        Style::default()
+    }
+
+    ///
+    /// Add a declaration.
+    ///
+    pub fn with(mut self, property: impl AsRef<str>, value: impl fmt::Display) -> Self {
+        self.text.push_str(property.as_ref());
+        let property = self.text.len();
+        self.failed |= write!(self.text, "{}", value).is_err();
+        self.ends.push((property, self.text.len()));
+        self
+    }
+
+    ///
+    /// Add a declaration if the condition holds.
+    ///
+    pub fn with_if(self, cond: bool, property: impl AsRef<str>, value: impl fmt::Display) -> Self {
+        if cond {
+            self.with(property, value)
+        } else {
+            self
+        }
+    }
+
+    ///
+    /// Add the declarations of an iterator.
+    ///
+    pub fn with_all<I: IntoIterator<Item = (K, V)>, K: AsRef<str>, V: fmt::Display>(
+        self,
+        iter: I,
+    ) -> Self {
+        iter.into_iter().fold(self, |a, (k, v)| a.with(k, v))
+    }
+}
+
+impl<K: AsRef<str>, V: fmt::Display> FromIterator<(K, V)> for Style {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Style::new().with_all(iter)
+    }
+}
+
+impl Attr for Style {
+    fn render(self, w: &mut AttrWrite) -> Result<(), Error> {
+        if self.failed {
+            return Err(fmt::Error.into());
+        }
+        let mut start = 0;
+        for (property, value) in self.ends {
+            w.add_style(&self.text[start..property], &self.text[property..value])?;
+            start = value;
+        }
+        Ok(())
     }
 }
 
@@ -99,14 +926,14 @@ impl<A: fmt::Display, B: fmt::Display> Attr for (A, B) {
 pub struct AttrClosure<I> {
     func: I,
 }
-impl<F: FnOnce(&mut AttrWrite) -> fmt::Result> Attr for AttrClosure<F> {
-    fn render(self, w: &mut AttrWrite) -> fmt::Result {
+impl<F: FnOnce(&mut AttrWrite) -> Result<(), Error>> Attr for AttrClosure<F> {
+    fn render(self, w: &mut AttrWrite) -> Result<(), Error> {
         (self.func)(w)
     }
 }
 impl<F> AttrClosure<F>
 where
-    F: FnOnce(&mut AttrWrite) -> fmt::Result,
+    F: FnOnce(&mut AttrWrite) -> Result<(), Error>,
 {
     pub fn new(func: F) -> Self {
         AttrClosure { func }
@@ -123,13 +950,16 @@ pub struct Path<I> {
 }
 
 impl<I: IntoIterator<Item = PathCommand<D>>, D: fmt::Display> Attr for Path<I> {
-    fn render(self, w: &mut AttrWrite) -> std::fmt::Result {
-        w.writer_escapable().write_str(" d=\"")?;
+    fn render(self, w: &mut AttrWrite) -> Result<(), Error> {
+        w.attr_sep()?;
+        w.write_name(&tools::Name::new("d"))?;
+        w.writer_escapable().write_str("=\"")?;
 
         for command in self.iter {
             command.write(w.writer())?;
         }
-        w.writer_escapable().write_str("\"")
+        w.writer_escapable().write_str("\"")?;
+        Ok(())
     }
 }
 impl<I: IntoIterator<Item = PathCommand<D>>, D: fmt::Display> Path<I> {
@@ -193,10 +1023,13 @@ impl<F: FnOnce(PathSinkBuilder) -> fmt::Result> PathClosure<F> {
     }
 }
 impl<F: FnOnce(PathSinkBuilder) -> fmt::Result> Attr for PathClosure<F> {
-    fn render(self, w: &mut AttrWrite) -> fmt::Result {
-        w.writer_escapable().write_str(" d=\"")?;
+    fn render(self, w: &mut AttrWrite) -> Result<(), Error> {
+        w.attr_sep()?;
+        w.write_name(&tools::Name::new("d"))?;
+        w.writer_escapable().write_str("=\"")?;
         (self.func)(PathSinkBuilder { writer: w })?;
-        w.writer_escapable().write_str("\"")
+        w.writer_escapable().write_str("\"")?;
+        Ok(())
     }
 }
 
@@ -215,12 +1048,15 @@ impl<I: IntoIterator<Item = (D, D)>, D: fmt::Display> Points<I> {
     }
 }
 impl<I: IntoIterator<Item = (D, D)>, D: fmt::Display> Attr for Points<I> {
-    fn render(self, w: &mut AttrWrite) -> std::fmt::Result {
-        w.writer_escapable().write_str(" points=\"")?;
+    fn render(self, w: &mut AttrWrite) -> Result<(), Error> {
+        w.attr_sep()?;
+        w.write_name(&tools::Name::new("points"))?;
+        w.writer_escapable().write_str("=\"")?;
         for (x, y) in self.iter {
             write!(w.writer(), "{},{} ", x, y)?;
         }
-        w.writer_escapable().write_str("\"")
+        w.writer_escapable().write_str("\"")?;
+        Ok(())
     }
 }
 
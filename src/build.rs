@@ -22,6 +22,21 @@ pub fn raw<D: fmt::Display>(data: D) -> Raw<D> {
     Raw::new(data)
 }
 
+///
+/// Create a text node from anything that implements `Display`.
+///
+/// ```
+/// use tagu::build;
+/// use tagu::prelude::*;
+/// let k = build::elem("p").inline().append(build::text(format_move!("{} > {}", 2, 1)));
+/// let mut s = String::new();
+/// tagu::render(k, &mut s).unwrap();
+/// assert_eq!(s, "<p>2 &gt; 1</p>\n");
+/// ```
+pub fn text<D: fmt::Display>(data: D) -> Text<D> {
+    Text::new(data)
+}
+
 ///
 /// Create an escapable element from a closure
 ///
@@ -39,7 +54,7 @@ pub fn raw<D: fmt::Display>(data: D) -> Raw<D> {
 /// ```
 ///
 #[deprecated(note = "use tagu::session")]
-pub fn from_closure_escapable<F: FnOnce(&mut ElemWriteEscapable) -> fmt::Result>(
+pub fn from_closure_escapable<F: FnOnce(&mut ElemWriteEscapable) -> Result<(), Error>>(
     func: F,
 ) -> ClosureEscapable<F> {
     ClosureEscapable::new(func)
@@ -60,7 +75,7 @@ pub fn from_closure_escapable<F: FnOnce(&mut ElemWriteEscapable) -> fmt::Result>
 /// ```
 ///
 #[deprecated(note = "use tagu::session")]
-pub fn from_closure<F: FnOnce(&mut ElemWrite) -> fmt::Result>(func: F) -> Closure<F> {
+pub fn from_closure<F: FnOnce(&mut ElemWrite) -> Result<(), Error>>(func: F) -> Closure<F> {
     Closure::new(func)
 }
 
@@ -72,14 +87,14 @@ pub fn from_closure2<F: FnOnce() -> E, E: Elem>(func: F) -> Closure2<F> {
 use crate::stack::*;
 pub fn from_stack<F>(func: F) -> Sess<F>
 where
-    F: FnOnce(ElemStack<Sentinel>) -> Result<ElemStack<Sentinel>, fmt::Error>,
+    F: FnOnce(ElemStack<Sentinel>) -> Result<ElemStack<Sentinel>, Error>,
 {
     Sess::new(func)
 }
 
 pub fn from_stack_escapable<F>(func: F) -> SessEscapable<F>
 where
-    F: FnOnce(ElemStackEscapable<Sentinel>) -> Result<ElemStackEscapable<Sentinel>, fmt::Error>,
+    F: FnOnce(ElemStackEscapable<Sentinel>) -> Result<ElemStackEscapable<Sentinel>, Error>,
 {
     SessEscapable::new(func)
 }
@@ -107,10 +122,109 @@ pub fn from_iter<I: Iterator<Item = R>, R: Elem>(iter: I) -> Iter<I> {
 /// tagu::render(k,&mut s).unwrap()
 ///
 /// ```
-pub fn single<D: fmt::Display>(tag: D) -> Single<D, (), &'static str, &'static str> {
+pub fn single<D: tools::AsName>(tag: D) -> Single<D, (), &'static str, &'static str> {
     Single::new(tag)
 }
 
+///
+/// Create a document type declaration.
+///
+/// ```
+/// let mut s = String::new();
+/// let k = tagu::build::doctype("html");
+/// tagu::render(k,&mut s).unwrap();
+/// assert_eq!(s, "<!DOCTYPE html>\n");
+///
+/// let mut s = String::new();
+/// let k = tagu::build::doctype("svg").with_public(
+///     "-//W3C//DTD SVG 1.1//EN",
+///     "http://www.w3.org/Graphics/SVG/1.1/DTD/svg11.dtd",
+/// );
+/// tagu::render(k, &mut s).unwrap();
+/// assert_eq!(
+///     s,
+///     "<!DOCTYPE svg PUBLIC \"-//W3C//DTD SVG 1.1//EN\" \"http://www.w3.org/Graphics/SVG/1.1/DTD/svg11.dtd\">\n"
+/// );
+///
+/// let k = tagu::build::doctype("a").with_system("\"");
+/// assert!(tagu::render(k, &mut String::new()).is_err());
+/// ```
+pub fn doctype<D: fmt::Display>(name: D) -> Doctype<D> {
+    Doctype::new(name)
+}
+
+///
+/// Create an xml declaration. It has to come first in the document.
+///
+/// ```
+/// let mut s = String::new();
+/// let k = tagu::build::xml_decl("1.0").with_encoding("UTF-8").with_standalone(true);
+/// tagu::render(k, &mut s).unwrap();
+/// assert_eq!(s, "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n");
+///
+/// let k = tagu::build::xml_decl(format_args!("1.{}", 1));
+/// assert_eq!(tagu::render_to_string(k).unwrap(), "<?xml version=\"1.1\"?>\n");
+///
+/// let k = tagu::build::xml_decl(2);
+/// assert!(tagu::render(k, &mut String::new()).is_err());
+/// ```
+pub fn xml_decl<V: fmt::Display>(version: V) -> XmlDecl<V> {
+    XmlDecl::new(version)
+}
+
+///
+/// Create a processing instruction. Rendering fails if the target is not a name without colons,
+/// or is `xml`, or if the data contains `?>`.
+///
+/// ```
+/// let mut s = String::new();
+/// let k = tagu::build::processing_instruction("xml-stylesheet", "href=\"a.css\"");
+/// tagu::render(k, &mut s).unwrap();
+/// assert_eq!(s, "<?xml-stylesheet href=\"a.css\"?>\n");
+///
+/// let k = tagu::build::processing_instruction("php", "echo 1 ?> 2");
+/// assert!(tagu::render(k, &mut String::new()).is_err());
+///
+/// let k = tagu::build::processing_instruction("a:b", "");
+/// assert!(tagu::render(k, &mut String::new()).is_err());
+/// ```
+pub fn processing_instruction<T: fmt::Display, D: fmt::Display>(
+    target: T,
+    data: D,
+) -> ProcessingInstruction<T, D> {
+    ProcessingInstruction::new(target, data)
+}
+
+///
+/// Create a CDATA section. `]]>` in the data is split across two sections.
+/// Html has no CDATA sections, so it fails the render in `Mode::Html`.
+///
+/// ```
+/// let mut s = String::new();
+/// let k = tagu::build::cdata("if (a[b[0]]>c && d < e) {}");
+/// tagu::render(k, &mut s).unwrap();
+/// assert_eq!(s, "<![CDATA[if (a[b[0]]]]><![CDATA[>c && d < e) {}]]>\n");
+///
+/// let e = tagu::render_html(tagu::build::cdata("a"), &mut String::new()).unwrap_err();
+/// assert!(matches!(e.kind(), tagu::ErrorKind::HtmlCdata));
+/// ```
+pub fn cdata<D: fmt::Display>(data: D) -> Cdata<D> {
+    Cdata::new(data)
+}
+
+///
+/// Create a comment. Rendering fails if the content would end the comment early.
+///
+/// ```
+/// let mut s = String::new();
+/// tagu::render(tagu::build::comment("a < b"), &mut s).unwrap();
+/// assert_eq!(s, "<!--a < b-->\n");
+/// assert!(tagu::render(tagu::build::comment("a -- b"), &mut s).is_err());
+/// ```
+pub fn comment<D: fmt::Display>(data: D) -> Comment<D> {
+    Comment::new(data)
+}
+
 ///
 /// Create an element.
 ///
@@ -120,10 +234,100 @@ pub fn single<D: fmt::Display>(tag: D) -> Single<D, (), &'static str, &'static s
 /// tagu::render(k,&mut s).unwrap()
 ///
 /// ```
-pub fn elem<D: fmt::Display>(tag: D) -> Element<D, ()> {
+pub fn elem<D: tools::AsName>(tag: D) -> Element<D, ()> {
     Element::new(tag)
 }
 
+///
+/// Create an element in a namespace. The namespace is declared on it unless it is in scope already.
+///
+/// ```
+/// use tagu::build;
+/// use tagu::ns;
+/// use tagu::prelude::*;
+/// let link = build::elem_ns(ns::SVG, "a")
+///     .with(build::attr_ns(ns::XLINK, "href", "#c"))
+///     .inline();
+/// let k = build::elem_ns(ns::SVG, "svg")
+///     .append(link)
+///     .append(build::single_ns(ns::SVG, "use").with(build::attr_ns(ns::XLINK, "href", "#c")));
+/// let mut s = String::new();
+/// tagu::render(k, &mut s).unwrap();
+/// let xlink = r##"xmlns:xlink="http://www.w3.org/1999/xlink" xlink:href="#c""##;
+/// assert_eq!(
+///     s,
+///     format!(
+///         "<svg xmlns=\"http://www.w3.org/2000/svg\">\n\t<a {0}></a>\n\t<use {0}/>\n</svg>\n",
+///         xlink
+///     )
+/// );
+///
+/// let k = build::elem_ns(ns::SVG.with_prefix("s"), "svg")
+///     .append(build::single_ns(ns::SVG.with_prefix("s"), "g"));
+/// let mut s = String::new();
+/// tagu::render_compact(k, &mut s).unwrap();
+/// assert_eq!(s, r#"<s:svg xmlns:s="http://www.w3.org/2000/svg"><s:g/></s:svg>"#);
+///
+/// let k = build::single_ns(ns::SVG.with_prefix("s"), "svg")
+///     .with(build::attr_ns(ns::XLINK.with_prefix("s"), "href", ""));
+/// let e = tagu::render(k, &mut String::new()).unwrap_err();
+/// assert!(matches!(e.kind(), tagu::ErrorKind::Namespace));
+/// ```
+pub fn elem_ns<D: tools::AsName>(ns: ns::Namespace, tag: D) -> Element<D, ()> {
+    Element::with_ns(ns, tag)
+}
+
+///
+/// Create an element in a namespace that has no closing tag.
+///
+pub fn single_ns<D: tools::AsName>(
+    ns: ns::Namespace,
+    tag: D,
+) -> Single<D, (), &'static str, &'static str> {
+    Single::with_ns(ns, tag)
+}
+
+///
+/// Create an attribute in a namespace. The namespace must have a prefix.
+///
+pub fn attr_ns<K: fmt::Display, V: fmt::Display>(
+    ns: ns::Namespace,
+    key: K,
+    value: V,
+) -> AttrNs<K, V> {
+    AttrNs::new(ns, key, value)
+}
+
+///
+/// Create a `class` attribute from an iterator of classes. See `attr::Classes`.
+///
+/// ```
+/// use tagu::build;
+/// use tagu::prelude::*;
+/// let k = build::single("td").with(attrs!(build::classes(["a", "b"]), build::classes(["b", "c"])));
+/// assert_eq!(tagu::render_to_string(k).unwrap(), "<td class=\"a b c\"/>\n");
+/// ```
+///
+pub fn classes<'a, I: IntoIterator>(iter: I) -> Classes<'a>
+where
+    I::Item: Into<std::borrow::Cow<'a, str>>,
+{
+    Classes::from_iter(iter)
+}
+
+///
+/// Create a `style` attribute from an iterator of css declarations. See `attr::Style`.
+///
+/// ```
+/// use tagu::build;
+/// let k = build::single("rect").with(build::style([("fill", "blue"), ("stroke", "red")]));
+/// assert_eq!(tagu::render_to_string(k).unwrap(), "<rect style=\"fill:blue;stroke:red\"/>\n");
+/// ```
+///
+pub fn style<I: IntoIterator<Item = (K, V)>, K: AsRef<str>, V: fmt::Display>(iter: I) -> Style {
+    Style::from_iter(iter)
+}
+
 ///
 /// Box an element
 ///
@@ -134,10 +338,24 @@ pub fn elem<D: fmt::Display>(tag: D) -> Element<D, ()> {
 /// tagu::render(k,&mut s).unwrap()
 ///
 /// ```
-pub fn box_elem<'a, E: Elem + 'a>(elem: E) -> DynamicElement<'a> {
+pub fn box_elem<'a, E: Elem + Locked + 'a>(elem: E) -> DynamicElement<'a> {
     DynamicElement::new(elem)
 }
 
+///
+/// Box an escapable element
+///
+/// ```
+/// let mut s = String::new();
+/// let k = tagu::build::raw_escapable("<test/>");
+/// let k = tagu::build::box_elem_escapable(k);
+/// tagu::render_escapable(k,&mut s).unwrap()
+///
+/// ```
+pub fn box_elem_escapable<'a, E: Elem + 'a>(elem: E) -> DynamicElementEscapable<'a> {
+    DynamicElementEscapable::new(elem)
+}
+
 ///
 /// Create an attr from a closure.
 ///
@@ -153,7 +371,9 @@ pub fn box_elem<'a, E: Elem + 'a>(elem: E) -> DynamicElement<'a> {
 ///
 /// ```
 #[deprecated]
-pub fn attr_from_closure<F: FnOnce(&mut AttrWrite) -> fmt::Result>(func: F) -> AttrClosure<F> {
+pub fn attr_from_closure<F: FnOnce(&mut AttrWrite) -> Result<(), Error>>(
+    func: F,
+) -> AttrClosure<F> {
     AttrClosure::new(func)
 }
 
@@ -2,37 +2,46 @@
 //! Elem trait and building blocks
 //!
 
-use crate::render::PrettyFmt;
+use crate::html::{self, TagKind};
+use crate::render::{Fmt, Mode, State};
 
 use super::*;
 
 ///
 /// Writer struct passed to escapable closure elem
 ///
-pub struct ElemWriteEscapable<'a>(WriteWrap<'a>, pub(crate) &'a mut PrettyFmt);
+pub struct ElemWriteEscapable<'a>(
+    SinkWrap<'a>,
+    pub(crate) &'a mut dyn Fmt,
+    pub(crate) &'a mut State,
+);
 
 impl<'a> ElemWriteEscapable<'a> {
-    pub fn borrow_mut2(&mut self) -> ElemWriteEscapable {
-        ElemWriteEscapable(self.0.borrow_mut(), self.1)
+    pub fn borrow_mut2(&mut self) -> ElemWriteEscapable<'_> {
+        ElemWriteEscapable(self.0.borrow_mut(), self.1, self.2)
     }
-    pub fn writer_escapable(&mut self) -> WriteWrap {
-        self.0.borrow_mut()
+    pub fn writer_escapable(&mut self) -> WriteWrap<'_> {
+        self.2.scan = tools::Scan::default();
+        self.0.as_write()
     }
-    pub fn writer(&mut self) -> tools::EscapeGuard<WriteWrap> {
-        tools::escape_guard(self.0.borrow_mut())
+    ///
+    /// Write text content. It is escaped according to where it is in the document.
+    ///
+    pub fn writer(&mut self) -> tools::Escaper<WriteWrap<'_>, &mut tools::Scan> {
+        self.2.text_escaper(self.0.as_write())
     }
 
     #[deprecated(note = "use tagu::session")]
-    pub fn render<E: Elem>(&mut self, elem: E) -> fmt::Result {
+    pub fn render<E: Elem>(&mut self, elem: E) -> Result<(), Error> {
         let tail = elem.render_head(self.as_elem_write())?;
         tail.render(self.as_elem_write())
     }
-    fn as_elem_write(&mut self) -> ElemWrite {
-        ElemWrite(WriteWrap(self.0 .0), self.1)
+    fn as_elem_write(&mut self) -> ElemWrite<'_> {
+        ElemWrite(SinkWrap(self.0 .0), self.1, self.2)
     }
 
     #[deprecated(note = "use tagu::session")]
-    pub fn render_map<E: Elem, F: FnOnce() -> E>(&mut self, func: F) -> fmt::Result {
+    pub fn render_map<E: Elem, F: FnOnce() -> E>(&mut self, func: F) -> Result<(), Error> {
         let elem = func();
         let tail = elem.render_head(self.as_elem_write())?;
         tail.render(self.as_elem_write())
@@ -63,24 +72,31 @@ impl<'a> ElemWriteEscapable<'a> {
 /// Writer struct passed to closure elem
 ///
 #[must_use]
-pub struct ElemWrite<'a>(pub(crate) WriteWrap<'a>, pub(crate) &'a mut PrettyFmt);
+pub struct ElemWrite<'a>(
+    pub(crate) SinkWrap<'a>,
+    pub(crate) &'a mut dyn Fmt,
+    pub(crate) &'a mut State,
+);
 
 impl<'a> ElemWrite<'a> {
-    pub(crate) fn borrow_mut2(&mut self) -> ElemWrite {
-        ElemWrite(self.0.borrow_mut(), self.1)
+    pub(crate) fn borrow_mut2(&mut self) -> ElemWrite<'_> {
+        ElemWrite(self.0.borrow_mut(), self.1, self.2)
     }
 
-    pub fn writer(&mut self) -> tools::EscapeGuard<WriteWrap> {
-        tools::escape_guard(self.0.borrow_mut())
+    ///
+    /// Write text content. It is escaped according to where it is in the document.
+    ///
+    pub fn writer(&mut self) -> tools::Escaper<WriteWrap<'_>, &mut tools::Scan> {
+        self.2.text_escaper(self.0.as_write())
     }
 
     #[deprecated(note = "use tagu::session")]
-    pub fn render<E: Elem + Locked>(&mut self, elem: E) -> fmt::Result {
+    pub fn render<E: Elem + Locked>(&mut self, elem: E) -> Result<(), Error> {
         self.render_inner(elem)
     }
 
     #[deprecated(note = "use tagu::session")]
-    pub fn render_map<E: Elem + Locked, F: FnOnce() -> E>(&mut self, func: F) -> fmt::Result {
+    pub fn render_map<E: Elem + Locked, F: FnOnce() -> E>(&mut self, func: F) -> Result<(), Error> {
         let elem = func();
         let tail = elem.render_head(self.borrow_mut2())?;
         tail.render(self.borrow_mut2())
@@ -95,9 +111,7 @@ impl<'a> ElemWrite<'a> {
     }
 
     fn swap_tab_type(&mut self, newt: &'static str) -> &'static str {
-        let ret = self.1.tab_char;
-        self.1.tab_char = newt;
-        ret
+        self.1.swap_tab(newt)
     }
 
     #[deprecated(note = "use tagu::session")]
@@ -120,8 +134,41 @@ impl<'a> ElemWrite<'a> {
         self.1.is_inline_mode()
     }
 
-    fn tabs(&mut self) -> fmt::Result {
-        self.1.tabs(&mut self.0)
+    ///
+    /// Start writing inline, unless already inline.
+    /// Returns whether inline mode has to be reset afterwards.
+    ///
+    fn start_inline(&mut self) -> Result<bool, Error> {
+        if self.is_inline_mode() {
+            Ok(false)
+        } else {
+            self.tabs()?;
+            self.set_inline_mode(true);
+            Ok(true)
+        }
+    }
+
+    ///
+    /// Write everything exactly as given until `end_preserve()`.
+    ///
+    fn start_preserve(&mut self) -> Result<Preserved, Error> {
+        let reset = self.start_inline()?;
+        let preserve = std::mem::replace(&mut self.2.preserve, true);
+        Ok(Preserved { reset, preserve })
+    }
+
+    fn end_preserve(&mut self, p: Preserved) -> Result<(), Error> {
+        self.2.preserve = p.preserve;
+        if p.reset {
+            self.set_inline_mode(false);
+            self.end_tag()?;
+        }
+        Ok(())
+    }
+
+    fn tabs(&mut self) -> Result<(), Error> {
+        self.1.tabs(&mut self.0)?;
+        Ok(())
     }
     fn push(&mut self) {
         self.1.push()
@@ -129,27 +176,150 @@ impl<'a> ElemWrite<'a> {
     fn pop(&mut self) {
         self.1.pop()
     }
-    fn end_tag(&mut self) -> fmt::Result {
-        self.1.end_tag(&mut self.0)
+    fn end_tag(&mut self) -> Result<(), Error> {
+        self.1.end_tag(&mut self.0)?;
+        Ok(())
     }
 
-    pub(crate) fn as_escapable(&mut self) -> ElemWriteEscapable {
-        ElemWriteEscapable(WriteWrap(self.0 .0), self.1)
+    ///
+    /// End a line of multi-line content. If `more` lines follow,
+    /// it is not joined with them.
+    ///
+    fn line_end(&mut self, more: bool) -> Result<(), Error> {
+        if more {
+            self.1.line_break(&mut self.0)?;
+        } else {
+            self.1.end_tag(&mut self.0)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn as_escapable(&mut self) -> ElemWriteEscapable<'_> {
+        ElemWriteEscapable(SinkWrap(self.0 .0), self.1, self.2)
+    }
+    pub(crate) fn writer_escapable(&mut self) -> WriteWrap<'_> {
+        // Markup ends whatever the text before it was in the middle of.
+        self.2.scan = tools::Scan::default();
+        self.0.as_write()
     }
-    pub(crate) fn writer_escapable(&mut self) -> WriteWrap {
-        self.0.borrow_mut()
+
+    fn name_writer(&mut self) -> tools::EscapeGuard<SinkWrap<'_>> {
+        tools::escape_guard(self.0.borrow_mut()).with_ascii(self.2.ascii)
+    }
+
+    ///
+    /// Write the attributes of the current element, and the declaration of its namespace.
+    /// They are collected first, so that duplicates can be dealt with.
+    ///
+    fn render_attrs<A: Attr>(&mut self, ns: Option<ns::Namespace>, attr: A) -> Result<(), Error> {
+        let mut buf = std::mem::take(&mut self.2.attrs);
+        let mut w = AttrWrite::new(&mut buf, self.2);
+        let res = ns
+            .map_or(Ok(()), |ns| w.declare(ns))
+            .and_then(|_| attr.render(&mut w))
+            .and_then(|_| w.finish())
+            .and_then(|_| self.write_attrs(&buf));
+        buf.clear();
+        self.2.attrs = buf;
+        res
+    }
+
+    fn write_attrs(&mut self, buf: &attr::AttrBuf) -> Result<(), Error> {
+        let html = self.2.mode == Mode::Html;
+        let same = |a: &str, b: &str| a == b || (html && a.eq_ignore_ascii_case(b));
+        self.0.write_str(buf.prefix())?;
+        let attrs = buf.attrs();
+        for (i, (name, text, namespace)) in attrs.clone().enumerate() {
+            let before = || attrs.clone().take(i).any(|a| same(a.0, name));
+            let after = || attrs.clone().skip(i + 1).any(|a| same(a.0, name));
+            let declared = || attrs.clone().any(|a| a.2 && same(a.0, name));
+            let keep = match self.2.duplicates {
+                attr::Duplicates::Allow => true,
+                // Namespace declarations are kept, as the namespace scopes rely on them.
+                _ if namespace => true,
+                attr::Duplicates::Error if declared() || before() => {
+                    return Err(Error::new(ErrorKind::DuplicateAttr(name.to_string())));
+                }
+                attr::Duplicates::Error => true,
+                attr::Duplicates::KeepFirst => !declared() && !before(),
+                attr::Duplicates::KeepLast => !declared() && !after(),
+            };
+            if keep {
+                self.1.attr_sep(&mut self.0)?;
+                self.0.write_str(text)?;
+            }
+        }
+        Ok(())
     }
 
-    fn as_attr_write(&mut self) -> AttrWrite {
-        attr::AttrWrite::new(self.0.borrow_mut())
+    ///
+    /// Classify the innermost open element.
+    ///
+    fn tag_kind(&mut self) -> TagKind {
+        match html::tag_kind(self.2.top_name()) {
+            TagKind::Void | TagKind::Preserve if self.2.mode == Mode::Xml => TagKind::Normal,
+            a => a,
+        }
     }
 
-    // fn new(w: &'a mut dyn fmt::Write, fmt: &'a mut dyn Fmt) -> Self {
-    //     ElemWrite(WriteWrap(w), fmt)
-    // }
-    pub(crate) fn render_inner<E: Elem>(&mut self, elem: E) -> fmt::Result {
+    ///
+    /// Write a name that is not a tag, like the target of a processing instruction,
+    /// failing if it is not an xml `Name`.
+    ///
+    fn write_checked_name<D: fmt::Display>(&mut self, name: &D) -> Result<(), Error> {
+        let mut n = tools::name_writer(self.0.borrow_mut(), self.2.ascii);
+        write!(n, "{}", name)
+            .and_then(|_| n.finish())
+            .map_err(|_| n.error(|| name.to_string()))
+    }
+
+    ///
+    /// Write the tag of the innermost open element.
+    ///
+    fn write_name(&mut self) -> Result<(), Error> {
+        // Checked when it was pushed.
+        self.0.write_str(self.2.top_name())?;
+        Ok(())
+    }
+
+    ///
+    /// Fail if the innermost open element can't have content.
+    ///
+    pub(crate) fn check_content(&self) -> Result<(), Error> {
+        if self.2.void {
+            return Err(Error::new(ErrorKind::VoidContent));
+        }
+        Ok(())
+    }
+
+    ///
+    /// Fail if markup can't be written here, as the innermost open element only holds text.
+    ///
+    fn check_markup(&self) -> Result<(), Error> {
+        if self.2.text_only {
+            return Err(Error::new(ErrorKind::TextOnly));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn render_inner<E: Elem>(&mut self, elem: E) -> Result<(), Error> {
+        self.check_content()?;
         let tail = elem.render_head(self.borrow_mut2())?;
-        tail.render(self.borrow_mut2())
+        tail.render(self.borrow_mut2())?;
+        self.boundary()
+    }
+
+    pub(crate) fn boundary(&mut self) -> Result<(), Error> {
+        self.0.boundary()?;
+        if self.2.auto_flush && self.2.depth() <= 1 {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn flush(&mut self) -> Result<(), Error> {
+        self.0.flush()?;
+        Ok(())
     }
 }
 
@@ -157,8 +327,8 @@ impl<'a> ElemWrite<'a> {
 /// Alternative trait for Elem that is friendly to dyn trait.
 ///
 trait ElemDyn {
-    fn render_head(&mut self, w: ElemWrite) -> Result<(), fmt::Error>;
-    fn render_tail(&mut self, w: ElemWrite) -> Result<(), fmt::Error>;
+    fn render_head(&mut self, w: ElemWrite) -> Result<(), Error>;
+    fn render_tail(&mut self, w: ElemWrite) -> Result<(), Error>;
 }
 
 ///
@@ -168,7 +338,7 @@ pub struct DynamicElementTail<'a> {
     elem: Box<dyn ElemDyn + 'a>,
 }
 impl<'a> ElemTail for DynamicElementTail<'a> {
-    fn render(mut self, w: ElemWrite) -> std::fmt::Result {
+    fn render(mut self, w: ElemWrite) -> Result<(), Error> {
         self.elem.render_tail(w)
     }
 }
@@ -178,10 +348,28 @@ impl Locked for DynamicElement<'_> {}
 ///
 /// A dynamic elem that implement Elem
 ///
-pub struct DynamicElement<'a> {
+pub struct DynamicElement<'a>(DynamicElementEscapable<'a>);
+
+impl<'a> DynamicElement<'a> {
+    pub fn new<E: Elem + Locked + 'a>(elem: E) -> Self {
+        DynamicElement(DynamicElementEscapable::new(elem))
+    }
+}
+
+impl<'a> Elem for DynamicElement<'a> {
+    type Tail = DynamicElementTail<'a>;
+    fn render_head(self, w: ElemWrite) -> Result<Self::Tail, Error> {
+        self.0.render_head(w)
+    }
+}
+
+///
+/// A dynamic escapable elem that implement Elem
+///
+pub struct DynamicElementEscapable<'a> {
     elem: Box<dyn ElemDyn + 'a>,
 }
-impl<'a> DynamicElement<'a> {
+impl<'a> DynamicElementEscapable<'a> {
     pub fn new<E: Elem + 'a>(elem: E) -> Self {
         ///
         /// A dynamic elem, that
@@ -200,25 +388,25 @@ impl<'a> DynamicElement<'a> {
             }
         }
         impl<E: Elem> ElemDyn for DynamicElem<E> {
-            fn render_head(&mut self, w: ElemWrite) -> Result<(), fmt::Error> {
+            fn render_head(&mut self, w: ElemWrite) -> Result<(), Error> {
                 let tail = self.head.take().unwrap().render_head(w)?;
                 self.tail = Some(tail);
                 Ok(())
             }
-            fn render_tail(&mut self, w: ElemWrite) -> Result<(), fmt::Error> {
+            fn render_tail(&mut self, w: ElemWrite) -> Result<(), Error> {
                 self.tail.take().unwrap().render(w)
             }
         }
 
-        DynamicElement {
+        DynamicElementEscapable {
             elem: Box::new(DynamicElem::new(elem)),
         }
     }
 }
 
-impl<'a> Elem for DynamicElement<'a> {
+impl<'a> Elem for DynamicElementEscapable<'a> {
     type Tail = DynamicElementTail<'a>;
-    fn render_head(mut self, w: ElemWrite) -> Result<Self::Tail, fmt::Error> {
+    fn render_head(mut self, w: ElemWrite) -> Result<Self::Tail, Error> {
         self.elem.render_head(w)?;
         Ok(DynamicElementTail { elem: self.elem })
     }
@@ -229,14 +417,22 @@ impl<'a> Elem for DynamicElement<'a> {
 ///
 pub trait Elem {
     type Tail: ElemTail;
-    fn render_head(self, w: ElemWrite) -> Result<Self::Tail, fmt::Error>;
+
+    ///
+    /// Whether this is character data. An element that has character data
+    /// appended to it has mixed content, and is written inline so that no
+    /// whitespace is added between its text and its children.
+    ///
+    const TEXT: bool = false;
+
+    fn render_head(self, w: ElemWrite) -> Result<Self::Tail, Error>;
 
     #[deprecated]
     fn render_closure<K>(
         self,
         mut w: ElemWrite,
-        func: impl FnOnce(ElemWrite) -> Result<K, fmt::Error>,
-    ) -> Result<K, fmt::Error>
+        func: impl FnOnce(ElemWrite) -> Result<K, Error>,
+    ) -> Result<K, Error>
     where
         Self: Sized,
     {
@@ -286,6 +482,46 @@ pub trait Elem {
         Inliner { elem: self }
     }
 
+    ///
+    /// Write this element and descendants exactly as given, without
+    /// adding any whitespace. In `Mode::Html`, this is done
+    /// automatically for `<pre>`, `<textarea>` and `<listing>`.
+    /// Their content gets an extra leading newline if it starts with one,
+    /// since html parsers drop the first.
+    ///
+    /// ```
+    /// use tagu::build;
+    /// use tagu::prelude::*;
+    /// let text = build::elem("text")
+    ///     .with(build::attr_ns(tagu::ns::XML, "space", "preserve"))
+    ///     .preserve()
+    ///     .append(build::elem("tspan").append(build::raw("  a\n  b")));
+    /// let k = build::elem("svg").append(text);
+    /// let mut s = String::new();
+    /// tagu::render(k, &mut s).unwrap();
+    /// assert_eq!(
+    ///     s,
+    ///     "<svg>\n\t<text xml:space=\"preserve\"><tspan>  a\n  b</tspan></text>\n</svg>\n"
+    /// );
+    ///
+    /// let k = build::elem("div").append(build::elem("pre").append(build::elem("code")));
+    /// let mut s = String::new();
+    /// tagu::render_html(k, &mut s).unwrap();
+    /// assert_eq!(s, "<div>\n\t<pre><code></code></pre>\n</div>\n");
+    ///
+    /// let k = build::elem("textarea").append("\nline");
+    /// let mut s = String::new();
+    /// tagu::render_html(k, &mut s).unwrap();
+    /// assert_eq!(s, "<textarea>\n\nline</textarea>\n");
+    /// ```
+    ///
+    fn preserve(self) -> Preserve<Self>
+    where
+        Self: Sized,
+    {
+        Preserve { elem: self }
+    }
+
     fn with_tab(self, new_tab: &'static str) -> WithTab<Self>
     where
         Self: Sized,
@@ -307,6 +543,49 @@ pub trait Elem {
 ///
 /// Indicates that the implementor does that allow arbitrary html escaping.
 ///
+/// Adaptors are only `Locked` if everything they wrap is `Locked`,
+/// so escapable elements can't be smuggled into `tagu::render()`:
+///
+/// ```compile_fail
+/// use tagu::prelude::*;
+/// let k = tagu::build::raw_escapable("<script>").inline();
+/// tagu::render(k, &mut String::new()).unwrap();
+/// ```
+///
+/// ```compile_fail
+/// use tagu::prelude::*;
+/// let k = tagu::build::raw_escapable("<script>").with_tab(" ");
+/// tagu::render(k, &mut String::new()).unwrap();
+/// ```
+///
+/// ```compile_fail
+/// use tagu::prelude::*;
+/// let k = tagu::build::raw_escapable("<script>").preserve();
+/// tagu::render(k, &mut String::new()).unwrap();
+/// ```
+///
+/// ```compile_fail
+/// let k = tagu::build::box_elem(tagu::build::raw_escapable("<script>"));
+/// tagu::render(k, &mut String::new()).unwrap();
+/// ```
+///
+/// ```compile_fail
+/// let k = tagu::build::box_elem_escapable(tagu::build::raw_escapable("<script>"));
+/// tagu::render(k, &mut String::new()).unwrap();
+/// ```
+///
+/// ```compile_fail
+/// #![allow(deprecated)]
+/// let k = tagu::build::from_closure2(|| tagu::build::raw_escapable("<script>"));
+/// tagu::render(k, &mut String::new()).unwrap();
+/// ```
+///
+/// ```compile_fail
+/// use tagu::prelude::*;
+/// let k = tagu::build::elem("a").append(tagu::build::raw_escapable("<script>"));
+/// tagu::render(k, &mut String::new()).unwrap();
+/// ```
+///
 pub trait Locked {}
 
 #[must_use]
@@ -319,12 +598,13 @@ pub struct AppendWith<A, B> {
 impl<A: Locked, B: FnOnce() -> C, C: Locked> Locked for AppendWith<A, B> {}
 
 impl<A: Elem, B: FnOnce() -> K, K: Elem> Elem for AppendWith<A, B> {
-    type Tail = A::Tail;
-    fn render_head(self, mut w: ElemWrite) -> Result<Self::Tail, fmt::Error> {
+    type Tail = InlinerTail<A::Tail>;
+    fn render_head(self, mut w: ElemWrite) -> Result<Self::Tail, Error> {
         let AppendWith { top, bottom } = self;
+        let reset = K::TEXT && w.start_inline()?;
         let tail = top.render_head(w.borrow_mut2())?;
         w.render_inner(bottom())?;
-        Ok(tail)
+        Ok(InlinerTail { reset, tail })
     }
 }
 
@@ -341,19 +621,20 @@ pub struct Append<A, B> {
 impl<A: Locked, B: Locked> Locked for Append<A, B> {}
 
 impl<A: Elem, B: Elem> Elem for Append<A, B> {
-    type Tail = A::Tail;
-    fn render_head(self, mut w: ElemWrite) -> Result<Self::Tail, fmt::Error> {
+    type Tail = InlinerTail<A::Tail>;
+    fn render_head(self, mut w: ElemWrite) -> Result<Self::Tail, Error> {
         let Append { top, bottom } = self;
+        let reset = B::TEXT && w.start_inline()?;
         let tail = top.render_head(w.borrow_mut2())?;
         w.render_inner(bottom)?;
-        Ok(tail)
+        Ok(InlinerTail { reset, tail })
     }
 }
 
 impl<A: Locked> Locked for Option<A> {}
 
 impl<A: ElemTail> ElemTail for Option<A> {
-    fn render(self, w: ElemWrite) -> std::fmt::Result {
+    fn render(self, w: ElemWrite) -> Result<(), Error> {
         if let Some(a) = self {
             a.render(w)?;
         }
@@ -362,7 +643,8 @@ impl<A: ElemTail> ElemTail for Option<A> {
 }
 impl<A: Elem> Elem for Option<A> {
     type Tail = Option<A::Tail>;
-    fn render_head(self, w: ElemWrite) -> Result<Self::Tail, fmt::Error> {
+    const TEXT: bool = A::TEXT;
+    fn render_head(self, w: ElemWrite) -> Result<Self::Tail, Error> {
         if let Some(a) = self {
             Ok(Some(a.render_head(w)?))
         } else {
@@ -384,7 +666,8 @@ impl<A: Locked, B: Locked> Locked for Chain<A, B> {}
 
 impl<A: Elem, B: Elem> Elem for Chain<A, B> {
     type Tail = B::Tail;
-    fn render_head(self, mut w: ElemWrite) -> Result<Self::Tail, fmt::Error> {
+    const TEXT: bool = A::TEXT || B::TEXT;
+    fn render_head(self, mut w: ElemWrite) -> Result<Self::Tail, Error> {
         let Chain { top, bottom } = self;
         w.render_inner(top)?;
         bottom.render_head(w)
@@ -395,7 +678,7 @@ impl<A: Elem, B: Elem> Elem for Chain<A, B> {
 /// Tail to elem trait.
 ///
 pub trait ElemTail {
-    fn render(self, w: ElemWrite) -> std::fmt::Result;
+    fn render(self, w: ElemWrite) -> Result<(), Error>;
 }
 
 ///
@@ -408,7 +691,10 @@ pub struct Session<'b, E> {
 }
 
 impl<'b, E: Elem> Session<'b, E> {
-    pub fn build(self, func: impl FnOnce(&mut ElemWrite) -> fmt::Result) -> fmt::Result {
+    pub fn build(
+        self,
+        func: impl FnOnce(&mut ElemWrite) -> Result<(), Error>,
+    ) -> Result<(), Error> {
         let Session { elem, mut writer } = self;
         let tail = elem.render_head(writer.borrow_mut2())?;
         func(&mut writer)?;
@@ -426,7 +712,10 @@ pub struct SessionEscapable<'b, E> {
 }
 
 impl<'b, E: Elem> SessionEscapable<'b, E> {
-    pub fn build(self, func: impl FnOnce(&mut ElemWriteEscapable) -> fmt::Result) -> fmt::Result {
+    pub fn build(
+        self,
+        func: impl FnOnce(&mut ElemWriteEscapable) -> Result<(), Error>,
+    ) -> Result<(), Error> {
         let SessionEscapable { elem, mut writer } = self;
         let tail = elem.render_head(writer.as_elem_write())?;
         func(&mut writer)?;
@@ -434,9 +723,9 @@ impl<'b, E: Elem> SessionEscapable<'b, E> {
     }
 }
 
-impl<I: FnOnce(&mut ElemWriteEscapable) -> fmt::Result> Elem for ClosureEscapable<I> {
+impl<I: FnOnce(&mut ElemWriteEscapable) -> Result<(), Error>> Elem for ClosureEscapable<I> {
     type Tail = ();
-    fn render_head(self, mut w: ElemWrite) -> Result<Self::Tail, fmt::Error> {
+    fn render_head(self, mut w: ElemWrite) -> Result<Self::Tail, Error> {
         (self.func)(&mut w.as_escapable())?;
         Ok(())
     }
@@ -451,7 +740,7 @@ pub struct ClosureEscapable<I> {
     func: I,
 }
 
-impl<I: FnOnce(&mut ElemWriteEscapable) -> fmt::Result> ClosureEscapable<I> {
+impl<I: FnOnce(&mut ElemWriteEscapable) -> Result<(), Error>> ClosureEscapable<I> {
     pub fn new(func: I) -> ClosureEscapable<I> {
         ClosureEscapable { func }
     }
@@ -461,11 +750,12 @@ pub struct Closure2<I> {
     pub func: I,
 }
 
-impl<I: FnOnce() -> E, E: Elem> Locked for Closure2<I> {}
+impl<I: FnOnce() -> E, E: Elem + Locked> Locked for Closure2<I> {}
 
 impl<I: FnOnce() -> E, E: Elem> Elem for Closure2<I> {
     type Tail = E::Tail;
-    fn render_head(self, w: ElemWrite) -> Result<Self::Tail, fmt::Error> {
+    const TEXT: bool = E::TEXT;
+    fn render_head(self, w: ElemWrite) -> Result<Self::Tail, Error> {
         let e = (self.func)();
         e.render_head(w)
     }
@@ -480,17 +770,17 @@ pub struct Closure<I> {
     func: I,
 }
 
-impl<I: FnOnce(&mut ElemWrite) -> fmt::Result> Closure<I> {
+impl<I: FnOnce(&mut ElemWrite) -> Result<(), Error>> Closure<I> {
     pub fn new(func: I) -> Closure<I> {
         Closure { func }
     }
 }
 
-impl<I: FnOnce(&mut ElemWrite) -> fmt::Result> Locked for Closure<I> {}
+impl<I: FnOnce(&mut ElemWrite) -> Result<(), Error>> Locked for Closure<I> {}
 
-impl<I: FnOnce(&mut ElemWrite) -> fmt::Result> Elem for Closure<I> {
+impl<I: FnOnce(&mut ElemWrite) -> Result<(), Error>> Elem for Closure<I> {
     type Tail = ();
-    fn render_head(self, mut w: ElemWrite) -> Result<Self::Tail, fmt::Error> {
+    fn render_head(self, mut w: ElemWrite) -> Result<Self::Tail, Error> {
         (self.func)(&mut w)?;
         Ok(())
     }
@@ -513,7 +803,8 @@ impl<I: IntoIterator<Item = R>, R: Locked> Locked for Iter<I> {}
 
 impl<I: IntoIterator<Item = R>, R: Elem> Elem for Iter<I> {
     type Tail = ();
-    fn render_head(self, mut w: ElemWrite) -> Result<Self::Tail, fmt::Error> {
+    const TEXT: bool = R::TEXT;
+    fn render_head(self, mut w: ElemWrite) -> Result<Self::Tail, Error> {
         for i in self.iter {
             w.render_inner(i)?;
         }
@@ -530,26 +821,143 @@ impl<D: fmt::Display> Raw<D> {
     pub fn new(data: D) -> Raw<D> {
         Raw { data }
     }
+
+    ///
+    /// Write each line on its own, indented to the current depth,
+    /// after removing the indentation that all lines share.
+    /// Blank lines at the start and end are dropped.
+    ///
+    /// Inline and preserved content is written unchanged.
+    ///
+    /// ```
+    /// use tagu::build;
+    /// use tagu::prelude::*;
+    /// let css = "
+    ///         p {
+    ///             color: red;
+    ///         }
+    ///     ";
+    /// let k = build::elem("head").append(build::elem("style").append(build::raw(css).indented()));
+    /// let mut s = String::new();
+    /// tagu::render_html(k, &mut s).unwrap();
+    /// assert_eq!(
+    ///     s,
+    ///     "<head>\n\t<style>\n\t\tp {\n\t\t    color: red;\n\t\t}\n\t</style>\n</head>\n"
+    /// );
+    /// ```
+    ///
+    pub fn indented(self) -> Indented<Self> {
+        Indented { elem: self }
+    }
 }
 
 impl<D: fmt::Display> Locked for Raw<D> {}
 impl<D: fmt::Display> Elem for Raw<D> {
     type Tail = ();
-    fn render_head(self, mut w: ElemWrite) -> Result<Self::Tail, fmt::Error> {
+    const TEXT: bool = true;
+    fn render_head(self, mut w: ElemWrite) -> Result<Self::Tail, Error> {
         //w.tabs()?;
-        write!(w.writer(), "{}", self.data)?;
+        let mut e = w.writer();
+        write!(e, "{}", self.data).map_err(|_| e.error())?;
         w.end_tag()?;
         Ok(())
     }
 }
 
-// impl<'a> Locked for &'a str {}
-// impl<'a> Elem for &'a str {
-//     type Tail = ();
-//     fn render_head(self, w: ElemWrite) -> Result<Self::Tail, fmt::Error> {
-//         Raw::new(self).render_head(w)
-//     }
-// }
+///
+/// A text node. It is escaped, and indented like any other node.
+///
+/// Strings, `char` and numbers are text nodes too. Elements that have text
+/// appended to them are written inline, so their text and children stay on one line.
+///
+/// ```
+/// use tagu::build;
+/// use tagu::prelude::*;
+/// let k = build::elem("p").append("a < b").append(build::elem("b").append(5));
+/// let mut s = String::new();
+/// tagu::render(k, &mut s).unwrap();
+/// assert_eq!(s, "<p>a &lt; b<b>5</b></p>\n");
+///
+/// let k = build::elem("div").append(elems!(
+///     build::elem("p").append("Hello ").append(build::elem("b").append("world")).append('!'),
+///     build::elem("p").append(build::elem("i").append("Bye"))
+/// ));
+/// let mut s = String::new();
+/// tagu::render(k, &mut s).unwrap();
+/// assert_eq!(
+///     s,
+///     "<div>\n\t<p>Hello <b>world</b>!</p>\n\t<p>\n\t\t<i>Bye</i>\n\t</p>\n</div>\n"
+/// );
+///
+/// // Adjacent text nodes are checked as one, so they can't end a script between them.
+/// let k = build::elem("script").append(elems!("</scr", "ipt>x"));
+/// let e = tagu::render_html(k, &mut String::new()).unwrap_err();
+/// assert!(matches!(e.kind(), tagu::ErrorKind::Escape));
+///
+/// let k = build::elem("style").append(elems!("<", "/", "style", ">"));
+/// assert!(tagu::render_html(k, &mut String::new()).is_err());
+/// ```
+///
+#[derive(Copy, Clone)]
+#[must_use]
+pub struct Text<D> {
+    data: D,
+}
+impl<D: fmt::Display> Text<D> {
+    pub fn new(data: D) -> Text<D> {
+        Text { data }
+    }
+}
+
+impl<D: fmt::Display> Locked for Text<D> {}
+impl<D: fmt::Display> Elem for Text<D> {
+    type Tail = ();
+    const TEXT: bool = true;
+    fn render_head(self, mut w: ElemWrite) -> Result<Self::Tail, Error> {
+        w.tabs()?;
+        let mut e = w.writer();
+        write!(e, "{}", self.data).map_err(|_| e.error())?;
+        w.end_tag()
+    }
+}
+
+macro_rules! text_elem {
+    ($($t:ty),*) => {
+        $(
+            impl Locked for $t {}
+            impl Elem for $t {
+                type Tail = ();
+                const TEXT: bool = true;
+                fn render_head(self, w: ElemWrite) -> Result<Self::Tail, Error> {
+                    Text::new(self).render_head(w)
+                }
+            }
+        )*
+    };
+}
+
+text_elem!(
+    &str,
+    &String,
+    String,
+    Box<str>,
+    std::borrow::Cow<'_, str>,
+    char,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    f32,
+    f64
+);
 
 ///
 /// A raw escapable element
@@ -563,10 +971,68 @@ impl<D: fmt::Display> RawEscapable<D> {
     pub fn new(data: D) -> RawEscapable<D> {
         RawEscapable { data }
     }
+
+    ///
+    /// Write each line indented to the current depth, like `Raw::indented()`.
+    ///
+    pub fn indented(self) -> Indented<Self> {
+        Indented { elem: self }
+    }
+}
+
+///
+/// Multi-line raw content that is re-indented to the current depth.
+///
+#[derive(Copy, Clone)]
+#[must_use]
+pub struct Indented<E> {
+    elem: E,
+}
+
+impl<D: fmt::Display> Locked for Indented<Raw<D>> {}
+impl<D: fmt::Display> Elem for Indented<Raw<D>> {
+    type Tail = ();
+    fn render_head(self, mut w: ElemWrite) -> Result<Self::Tail, Error> {
+        if w.is_inline_mode() {
+            return self.elem.render_head(w);
+        }
+        let mut data = String::new();
+        write!(data, "{}", self.elem.data)?;
+        let lines = tools::dedent(&data);
+        for (i, line) in lines.iter().enumerate() {
+            if !line.is_empty() {
+                w.tabs()?;
+                let mut e = w.writer();
+                e.write_str(line).map_err(|_| e.error())?;
+            }
+            w.line_end(i + 1 < lines.len())?;
+        }
+        Ok(())
+    }
+}
+
+impl<D: fmt::Display> Elem for Indented<RawEscapable<D>> {
+    type Tail = ();
+    fn render_head(self, mut w: ElemWrite) -> Result<Self::Tail, Error> {
+        if w.is_inline_mode() {
+            return self.elem.render_head(w);
+        }
+        let mut data = String::new();
+        write!(data, "{}", self.elem.data)?;
+        let lines = tools::dedent(&data);
+        for (i, line) in lines.iter().enumerate() {
+            if !line.is_empty() {
+                w.tabs()?;
+                w.writer_escapable().write_str(line)?;
+            }
+            w.line_end(i + 1 < lines.len())?;
+        }
+        Ok(())
+    }
 }
 impl<D: fmt::Display> Elem for RawEscapable<D> {
     type Tail = ();
-    fn render_head(self, mut w: ElemWrite) -> Result<Self::Tail, fmt::Error> {
+    fn render_head(self, mut w: ElemWrite) -> Result<Self::Tail, Error> {
         //w.tabs()?;
         write!(w.writer_escapable(), "{}", self.data)?;
         w.end_tag()?;
@@ -586,15 +1052,19 @@ pub struct Single<D, A, K, Z> {
     attr: A,
     start: K,
     ending: Z,
+    plain: bool,
+    ns: Option<ns::Namespace>,
 }
-impl<D: fmt::Display, A: Attr, K: fmt::Display, Z: fmt::Display> Locked for Single<D, A, K, Z> {}
-impl<D: fmt::Display, A: Attr, K, Z> Single<D, A, K, Z> {
+impl<D: tools::AsName, A: Attr, K: fmt::Display, Z: fmt::Display> Locked for Single<D, A, K, Z> {}
+impl<D: tools::AsName, A: Attr, K, Z> Single<D, A, K, Z> {
     pub fn with<AA: Attr>(self, attr: AA) -> Single<D, AttrChain<A, AA>, K, Z> {
         Single {
             tag: self.tag,
             attr: self.attr.chain(attr),
             ending: self.ending,
             start: self.start,
+            plain: self.plain,
+            ns: self.ns,
         }
     }
 
@@ -604,6 +1074,8 @@ impl<D: fmt::Display, A: Attr, K, Z> Single<D, A, K, Z> {
             attr: self.attr,
             ending,
             start: self.start,
+            plain: false,
+            ns: self.ns,
         }
     }
     pub fn with_start<KK: fmt::Display>(self, start: KK) -> Single<D, A, KK, Z> {
@@ -612,73 +1084,377 @@ impl<D: fmt::Display, A: Attr, K, Z> Single<D, A, K, Z> {
             attr: self.attr,
             ending: self.ending,
             start,
+            plain: false,
+            ns: self.ns,
         }
     }
 }
-impl<D: fmt::Display, A: Attr, K: fmt::Display, Z: fmt::Display> Elem for Single<D, A, K, Z> {
+impl<D: tools::AsName, A: Attr, K: fmt::Display, Z: fmt::Display> Elem for Single<D, A, K, Z> {
     type Tail = ();
-    fn render_head(self, mut w: ElemWrite) -> Result<Self::Tail, fmt::Error> {
+    fn render_head(self, mut w: ElemWrite) -> Result<Self::Tail, Error> {
         let Single {
             tag,
             attr,
             start,
             ending,
+            plain,
+            ns,
         } = self;
+        w.check_markup()?;
+        w.2.push_name(ns.and_then(|a| a.prefix()), &tag)?;
+        let mark = w.2.ns.open();
+        let html = plain && w.2.mode == Mode::Html;
+        let kind = if html { w.tag_kind() } else { TagKind::Normal };
         w.tabs()?;
         w.writer_escapable().write_char('<')?;
-        write!(w.writer(), "{}{}", start, tag)?;
+        write!(w.name_writer(), "{}", start)?;
+        w.write_name()?;
         //w.writer().write_char(' ')?;
-        attr.render(&mut w.as_attr_write())?;
-        write!(w.writer(), "{}", ending)?;
-        w.writer_escapable().write_str(">")?;
+        w.render_attrs(ns, attr)?;
+        if html {
+            w.writer_escapable().write_str(">")?;
+            if kind != TagKind::Void {
+                w.writer_escapable().write_str("</")?;
+                w.write_name()?;
+                w.writer_escapable().write_char('>')?;
+            }
+        } else {
+            write!(w.name_writer(), "{}", ending)?;
+            w.writer_escapable().write_str(">")?;
+        }
         w.end_tag()?;
+        w.2.ns.close(mark);
+        w.2.pop_name();
         Ok(())
     }
 }
 
-impl<D: fmt::Display> Single<D, (), &'static str, &'static str> {
+impl<D: tools::AsName> Single<D, (), &'static str, &'static str> {
     pub fn new(tag: D) -> Self {
         Single {
             tag,
             attr: (),
             start: "",
             ending: "/",
+            plain: true,
+            ns: None,
+        }
+    }
+
+    ///
+    /// An element with no ending tag in a namespace.
+    ///
+    pub fn with_ns(ns: ns::Namespace, tag: D) -> Self {
+        Single {
+            ns: Some(ns),
+            ..Single::new(tag)
         }
     }
 }
 
+///
+/// A document type declaration
+///
+#[derive(Copy, Clone)]
+#[must_use]
+pub struct Doctype<D, P = &'static str, S = &'static str> {
+    name: D,
+    public: Option<P>,
+    system: Option<S>,
+}
+impl<D: fmt::Display> Doctype<D> {
+    pub fn new(name: D) -> Self {
+        Doctype {
+            name,
+            public: None,
+            system: None,
+        }
+    }
+}
+impl<D: fmt::Display, P: fmt::Display, S: fmt::Display> Doctype<D, P, S> {
+    ///
+    /// Refer to an external DTD by its public identifier and system identifier.
+    ///
+    pub fn with_public<PP: fmt::Display, SS: fmt::Display>(
+        self,
+        public: PP,
+        system: SS,
+    ) -> Doctype<D, PP, SS> {
+        Doctype {
+            name: self.name,
+            public: Some(public),
+            system: Some(system),
+        }
+    }
+
+    ///
+    /// Refer to an external DTD by its system identifier.
+    ///
+    pub fn with_system<SS: fmt::Display>(self, system: SS) -> Doctype<D, P, SS> {
+        Doctype {
+            name: self.name,
+            public: None,
+            system: Some(system),
+        }
+    }
+}
+impl<D: fmt::Display, P: fmt::Display, S: fmt::Display> Locked for Doctype<D, P, S> {}
+impl<D: fmt::Display, P: fmt::Display, S: fmt::Display> Elem for Doctype<D, P, S> {
+    type Tail = ();
+    fn render_head(self, mut w: ElemWrite) -> Result<Self::Tail, Error> {
+        fn is_pubid_char(c: char) -> bool {
+            c.is_ascii_alphanumeric() || " \r\n-'()+,./:=?;!*#@$_%".contains(c)
+        }
+
+        w.check_markup()?;
+        w.tabs()?;
+        w.writer_escapable().write_str("<!DOCTYPE ")?;
+        w.write_checked_name(&self.name)?;
+        if let Some(public) = self.public {
+            let public = public.to_string();
+            if !public.chars().all(is_pubid_char) {
+                return Err(Error::new(ErrorKind::Declaration));
+            }
+            write!(w.writer_escapable(), " PUBLIC \"{}\"", public)?;
+        }
+        if let Some(system) = self.system {
+            let system = system.to_string();
+            if system.contains('"') || (w.2.ascii && !system.is_ascii()) {
+                return Err(Error::new(ErrorKind::Declaration));
+            }
+            write!(w.writer_escapable(), " \"{}\"", system)?;
+        }
+        w.writer_escapable().write_char('>')?;
+        w.end_tag()
+    }
+}
+
+///
+/// An xml declaration, like `<?xml version="1.0" encoding="UTF-8"?>`.
+///
+#[derive(Copy, Clone)]
+#[must_use]
+pub struct XmlDecl<V, E = &'static str> {
+    version: V,
+    encoding: Option<E>,
+    standalone: Option<bool>,
+}
+impl<V: fmt::Display> XmlDecl<V> {
+    pub fn new(version: V) -> Self {
+        XmlDecl {
+            version,
+            encoding: None,
+            standalone: None,
+        }
+    }
+}
+impl<V: fmt::Display, E: fmt::Display> XmlDecl<V, E> {
+    ///
+    /// Declare the encoding, like `UTF-8`.
+    ///
+    pub fn with_encoding<EE: fmt::Display>(self, encoding: EE) -> XmlDecl<V, EE> {
+        XmlDecl {
+            version: self.version,
+            encoding: Some(encoding),
+            standalone: self.standalone,
+        }
+    }
+
+    ///
+    /// Declare whether the document depends on external markup declarations.
+    ///
+    pub fn with_standalone(self, standalone: bool) -> Self {
+        XmlDecl {
+            standalone: Some(standalone),
+            ..self
+        }
+    }
+}
+impl<V: fmt::Display, E: fmt::Display> Locked for XmlDecl<V, E> {}
+impl<V: fmt::Display, E: fmt::Display> Elem for XmlDecl<V, E> {
+    type Tail = ();
+    fn render_head(self, mut w: ElemWrite) -> Result<Self::Tail, Error> {
+        w.check_markup()?;
+        let version = self.version.to_string();
+        let encoding = self.encoding.map(|a| a.to_string());
+        let valid_version = version
+            .strip_prefix("1.")
+            .is_some_and(|a| !a.is_empty() && a.bytes().all(|a| a.is_ascii_digit()));
+        let valid_encoding = encoding.as_deref().is_none_or(|a| {
+            a.starts_with(|c: char| c.is_ascii_alphabetic())
+                && a.chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+        });
+        if !valid_version || !valid_encoding {
+            return Err(Error::new(ErrorKind::Declaration));
+        }
+
+        w.tabs()?;
+        write!(w.writer_escapable(), "<?xml version=\"{}\"", version)?;
+        if let Some(a) = encoding {
+            write!(w.writer_escapable(), " encoding=\"{}\"", a)?;
+        }
+        if let Some(a) = self.standalone {
+            let a = if a { "yes" } else { "no" };
+            write!(w.writer_escapable(), " standalone=\"{}\"", a)?;
+        }
+        w.writer_escapable().write_str("?>")?;
+        w.end_tag()
+    }
+}
+
+///
+/// A processing instruction, like `<?xml-stylesheet href="a.css"?>`.
+///
+#[derive(Copy, Clone)]
+#[must_use]
+pub struct ProcessingInstruction<T, D> {
+    target: T,
+    data: D,
+}
+impl<T: fmt::Display, D: fmt::Display> ProcessingInstruction<T, D> {
+    pub fn new(target: T, data: D) -> Self {
+        ProcessingInstruction { target, data }
+    }
+}
+impl<T: fmt::Display, D: fmt::Display> Locked for ProcessingInstruction<T, D> {}
+impl<T: fmt::Display, D: fmt::Display> Elem for ProcessingInstruction<T, D> {
+    type Tail = ();
+    fn render_head(self, mut w: ElemWrite) -> Result<Self::Tail, Error> {
+        w.check_markup()?;
+        w.tabs()?;
+        w.writer_escapable().write_str("<?")?;
+        // `xml` and its other cases are reserved for the xml declaration,
+        // and namespaces leave no room for a colon.
+        let target = self.target.to_string();
+        if target.eq_ignore_ascii_case("xml") || target.contains(':') {
+            return Err(Error::new(ErrorKind::Name(target)));
+        }
+        w.write_checked_name(&target)?;
+        w.writer_escapable().write_char(' ')?;
+        let mut e =
+            w.2.escaper(w.0.borrow_mut(), tools::Context::ProcessingInstruction);
+        write!(e, "{}", self.data).map_err(|_| e.error())?;
+        w.writer_escapable().write_str("?>")?;
+        w.end_tag()
+    }
+}
+
+///
+/// A CDATA section
+///
+#[derive(Copy, Clone)]
+#[must_use]
+pub struct Cdata<D> {
+    data: D,
+}
+impl<D: fmt::Display> Cdata<D> {
+    pub fn new(data: D) -> Self {
+        Cdata { data }
+    }
+}
+impl<D: fmt::Display> Locked for Cdata<D> {}
+impl<D: fmt::Display> Elem for Cdata<D> {
+    type Tail = ();
+    const TEXT: bool = true;
+    fn render_head(self, mut w: ElemWrite) -> Result<Self::Tail, Error> {
+        if w.2.mode == Mode::Html {
+            return Err(Error::new(ErrorKind::HtmlCdata));
+        }
+        w.tabs()?;
+        w.writer_escapable().write_str("<![CDATA[")?;
+        let mut e = w.2.escaper(w.0.borrow_mut(), tools::Context::Cdata);
+        write!(e, "{}", self.data).map_err(|_| e.error())?;
+        w.writer_escapable().write_str("]]>")?;
+        w.end_tag()
+    }
+}
+
+///
+/// A comment
+///
+#[derive(Copy, Clone)]
+#[must_use]
+pub struct Comment<D> {
+    data: D,
+}
+impl<D: fmt::Display> Comment<D> {
+    pub fn new(data: D) -> Self {
+        Comment { data }
+    }
+}
+impl<D: fmt::Display> Locked for Comment<D> {}
+impl<D: fmt::Display> Elem for Comment<D> {
+    type Tail = ();
+    fn render_head(self, mut w: ElemWrite) -> Result<Self::Tail, Error> {
+        w.check_markup()?;
+        w.tabs()?;
+        w.writer_escapable().write_str("<!--")?;
+        let mut e = w.2.escaper(w.0.borrow_mut(), tools::Context::Comment);
+        write!(e, "{}", self.data).map_err(|_| e.error())?;
+        e.finish().map_err(|_| Error::new(ErrorKind::Escape))?;
+        w.writer_escapable().write_str("-->")?;
+        w.end_tag()
+    }
+}
+
 ///
 /// The tail of an element
 ///
 #[derive(Copy, Clone)]
 #[must_use]
-pub struct ElementTail<D> {
-    tag: D,
+pub struct ElementTail {
+    kind: TagKind,
+    raw_text: Option<&'static str>,
+    text_only: bool,
+    mark: ns::Mark,
+    preserved: Option<Preserved>,
 }
 
-impl<D: fmt::Display> ElemTail for ElementTail<D> {
-    fn render(self, mut w: ElemWrite) -> std::fmt::Result {
+impl ElemTail for ElementTail {
+    fn render(self, mut w: ElemWrite) -> Result<(), Error> {
+        w.2.raw_text = self.raw_text;
+        w.2.text_only = self.text_only;
+        w.2.ns.close(self.mark);
+        if self.kind == TagKind::Void {
+            w.2.void = false;
+            w.2.pop_name();
+            return Ok(());
+        }
         w.pop();
 
         //w.end_tag()?;
         w.tabs()?;
 
         w.writer_escapable().write_str("</")?;
-        write!(w.writer(), "{}", &self.tag)?;
+        w.write_name()?;
         w.writer_escapable().write_char('>')?;
         w.end_tag()?;
+        w.2.pop_name();
+        if let Some(p) = self.preserved {
+            w.end_preserve(p)?;
+        }
 
         Ok(())
     }
 }
 
+///
+/// What to restore once a preserved element is closed.
+///
+#[derive(Copy, Clone)]
+struct Preserved {
+    reset: bool,
+    preserve: bool,
+}
+
 pub struct InlinerTail<K: ElemTail> {
     reset: bool,
     tail: K,
 }
 
 impl<D: ElemTail> ElemTail for InlinerTail<D> {
-    fn render(self, mut w: ElemWrite) -> std::fmt::Result {
+    fn render(self, mut w: ElemWrite) -> Result<(), Error> {
         self.tail.render(w.borrow_mut2())?;
 
         if self.reset {
@@ -695,29 +1471,52 @@ impl<D: ElemTail> ElemTail for InlinerTail<D> {
 pub struct Inliner<E> {
     elem: E,
 }
-impl<E> Locked for Inliner<E> {}
+impl<E: Locked> Locked for Inliner<E> {}
 impl<E: Elem> Elem for Inliner<E> {
     type Tail = InlinerTail<E::Tail>;
-    fn render_head(self, mut w: ElemWrite) -> Result<Self::Tail, fmt::Error> {
-        let reset = if w.is_inline_mode() {
-            false
-        } else {
-            w.tabs()?;
-            w.set_inline_mode(true);
-            true
-        };
+    const TEXT: bool = E::TEXT;
+    fn render_head(self, mut w: ElemWrite) -> Result<Self::Tail, Error> {
+        let reset = w.start_inline()?;
         let tail = self.elem.render_head(w)?;
 
         Ok(InlinerTail { reset, tail })
     }
 }
 
+pub struct PreserveTail<T> {
+    tail: T,
+    preserved: Preserved,
+}
+impl<T: ElemTail> ElemTail for PreserveTail<T> {
+    fn render(self, mut w: ElemWrite) -> Result<(), Error> {
+        self.tail.render(w.borrow_mut2())?;
+        w.end_preserve(self.preserved)
+    }
+}
+
+#[derive(Copy, Clone)]
+#[must_use]
+pub struct Preserve<E> {
+    elem: E,
+}
+impl<E: Locked> Locked for Preserve<E> {}
+impl<E: Elem> Elem for Preserve<E> {
+    type Tail = PreserveTail<E::Tail>;
+    const TEXT: bool = E::TEXT;
+    fn render_head(self, mut w: ElemWrite) -> Result<Self::Tail, Error> {
+        let preserved = w.start_preserve()?;
+        let tail = self.elem.render_head(w.borrow_mut2())?;
+
+        Ok(PreserveTail { tail, preserved })
+    }
+}
+
 pub struct WithTabTail<T> {
     tail: T,
     original: &'static str,
 }
 impl<T: ElemTail> ElemTail for WithTabTail<T> {
-    fn render(self, mut w: ElemWrite) -> std::fmt::Result {
+    fn render(self, mut w: ElemWrite) -> Result<(), Error> {
         self.tail.render(w.borrow_mut2())?;
 
         let _ = w.swap_tab_type(self.original);
@@ -729,10 +1528,11 @@ pub struct WithTab<E> {
     new_tab: &'static str,
     elem: E,
 }
-impl<E> Locked for WithTab<E> {}
+impl<E: Locked> Locked for WithTab<E> {}
 impl<E: Elem> Elem for WithTab<E> {
     type Tail = WithTabTail<E::Tail>;
-    fn render_head(self, mut w: ElemWrite) -> Result<Self::Tail, fmt::Error> {
+    const TEXT: bool = E::TEXT;
+    fn render_head(self, mut w: ElemWrite) -> Result<Self::Tail, Error> {
         let original = w.swap_tab_type(self.new_tab);
         let tail = self.elem.render_head(w.borrow_mut2())?;
 
@@ -748,38 +1548,84 @@ impl<E: Elem> Elem for WithTab<E> {
 pub struct Element<D, A> {
     tag: D,
     attr: A,
+    ns: Option<ns::Namespace>,
 }
 
-impl<D: fmt::Display, A: Attr> Locked for Element<D, A> {}
+impl<D: tools::AsName, A: Attr> Locked for Element<D, A> {}
 
-impl<D: fmt::Display, A: Attr> Element<D, A> {
+impl<D: tools::AsName, A: Attr> Element<D, A> {
     pub fn with<AA: Attr>(self, attr: AA) -> Element<D, AttrChain<A, AA>> {
         Element {
             tag: self.tag,
             attr: self.attr.chain(attr),
+            ns: self.ns,
         }
     }
 }
-impl<D: fmt::Display, A: Attr> Elem for Element<D, A> {
-    type Tail = ElementTail<D>;
-    fn render_head(self, mut w: ElemWrite) -> Result<Self::Tail, fmt::Error> {
-        let Element { tag, attr } = self;
+impl<D: tools::AsName, A: Attr> Elem for Element<D, A> {
+    type Tail = ElementTail;
+    fn render_head(self, mut w: ElemWrite) -> Result<Self::Tail, Error> {
+        let Element { tag, attr, ns } = self;
+        w.check_markup()?;
+        w.2.push_name(ns.and_then(|a| a.prefix()), &tag)?;
+        let mark = w.2.ns.open();
+        let kind = w.tag_kind();
+        let preserved = if kind == TagKind::Preserve {
+            Some(w.start_preserve()?)
+        } else {
+            None
+        };
         w.tabs()?;
         w.writer_escapable().write_char('<')?;
-        write!(w.writer(), "{}", tag)?;
+        w.write_name()?;
         //w.writer().write_char(' ')?;
-        attr.render(&mut w.as_attr_write())?;
+        w.render_attrs(ns, attr)?;
         w.writer_escapable().write_str(">")?;
 
         w.end_tag()?;
 
-        w.push();
-        Ok(ElementTail { tag })
+        let raw_text = w.2.raw_text;
+        let text_only = w.2.text_only;
+        w.2.text_only = w.2.mode == Mode::Html && html::text_only(w.2.top_name());
+        match kind {
+            TagKind::Void => w.2.void = true,
+            TagKind::RawText(a) => {
+                w.2.raw_text = Some(a);
+                w.push();
+            }
+            TagKind::Preserve => {
+                w.2.scan.lead = true;
+                w.push();
+            }
+            TagKind::Normal => w.push(),
+        }
+        Ok(ElementTail {
+            kind,
+            raw_text,
+            text_only,
+            mark,
+            preserved,
+        })
     }
 }
-impl<D: fmt::Display> Element<D, ()> {
+impl<D: tools::AsName> Element<D, ()> {
     pub fn new(tag: D) -> Self {
-        Element { tag, attr: () }
+        Element {
+            tag,
+            attr: (),
+            ns: None,
+        }
+    }
+
+    ///
+    /// An element in a namespace.
+    ///
+    pub fn with_ns(ns: ns::Namespace, tag: D) -> Self {
+        Element {
+            tag,
+            attr: (),
+            ns: Some(ns),
+        }
     }
 }
 
@@ -799,12 +1645,38 @@ pub struct BufferedElem {
 }
 
 impl BufferedElem {
-    pub fn new<E: Elem + Locked>(elem: E) -> Result<Self, fmt::Error> {
-        let mut fmt = crate::render::PrettyFmt::new();
+    pub fn new<E: Elem + Locked>(elem: E) -> Result<Self, Error> {
+        Self::with_fmt(elem, crate::render::PrettyFmt::new())
+    }
+
+    ///
+    /// Buffer an element using the given formatter.
+    ///
+    /// ```
+    /// use tagu::build;
+    /// use tagu::elem::BufferedElem;
+    /// use tagu::prelude::*;
+    /// let k = build::elem("a").append(build::single("b"));
+    /// let b = BufferedElem::with_fmt(k, tagu::render::NoFmt).unwrap();
+    /// assert_eq!(b.into_parts(), ("<a><b/>".to_string(), "</a>".to_string()));
+    /// ```
+    pub fn with_fmt<E: Elem + Locked, F: Fmt>(elem: E, mut fmt: F) -> Result<Self, Error> {
         let mut head = String::new();
         let mut tail = String::new();
-        let t = elem.render_head(ElemWrite(WriteWrap(&mut head), &mut fmt))?;
-        t.render(ElemWrite(WriteWrap(&mut tail), &mut fmt))?;
+        let mut state = State::new(Mode::Xml);
+        let t = elem
+            .render_head(ElemWrite(
+                SinkWrap(&mut tools::Plain(&mut head)),
+                &mut fmt,
+                &mut state,
+            ))
+            .map_err(|e| e.with_path(state.path()))?;
+        t.render(ElemWrite(
+            SinkWrap(&mut tools::Plain(&mut tail)),
+            &mut fmt,
+            &mut state,
+        ))
+        .map_err(|e| e.with_path(state.path()))?;
         head.shrink_to_fit();
         tail.shrink_to_fit();
         Ok(BufferedElem { head, tail })
@@ -822,22 +1694,23 @@ pub struct BufferedTail<'a> {
     tail: &'a str,
 }
 impl<'a> ElemTail for BufferedTail<'a> {
-    fn render(self, mut w: ElemWrite) -> std::fmt::Result {
-        write!(w.writer_escapable(), "{}", self.tail)
+    fn render(self, mut w: ElemWrite) -> Result<(), Error> {
+        write!(w.writer_escapable(), "{}", self.tail)?;
+        Ok(())
     }
 }
-impl<'a> Locked for &'a BufferedElem {}
+impl Locked for &BufferedElem {}
 
 impl<'a> Elem for &'a BufferedElem {
     type Tail = BufferedTail<'a>;
-    fn render_head(self, mut w: ElemWrite) -> Result<Self::Tail, fmt::Error> {
+    fn render_head(self, mut w: ElemWrite) -> Result<Self::Tail, Error> {
         write!(w.writer_escapable(), "{}", self.head)?;
         Ok(BufferedTail { tail: &self.tail })
     }
 }
 
 impl ElemTail for () {
-    fn render(self, _: ElemWrite) -> std::fmt::Result {
+    fn render(self, _: ElemWrite) -> Result<(), Error> {
         Ok(())
     }
 }
@@ -0,0 +1,167 @@
+//!
+//! The error type returned when rendering fails.
+//!
+
+use std::fmt;
+
+///
+/// A rendering error.
+///
+/// Carries what went wrong as well as the path of elements that were open when it did.
+///
+/// ```
+/// use tagu::build;
+/// use tagu::prelude::*;
+/// let k = build::elem("svg").append(build::elem("g").append(tagu::util::comment("a--b")));
+/// let e = tagu::render(k, &mut String::new()).unwrap_err();
+/// assert!(matches!(e.kind(), tagu::ErrorKind::Escape));
+/// assert_eq!(e.path(), ["svg", "g"]);
+/// ```
+///
+#[derive(Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    path: Vec<String>,
+}
+
+///
+/// The kind of rendering error.
+///
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The writer failed, or a `Display` implementation returned an error.
+    Fmt,
+    /// The underlying `std::io::Write` failed.
+    Io(std::io::Error),
+    /// Content would have escaped its context, like `</script>` inside a script or `--` inside a comment.
+    Escape,
+    /// A character that xml does not allow, with `InvalidChars::Error`. See `tools::InvalidChars`.
+    InvalidChar(char),
+    /// A tag or attribute name is not an xml `Name`. Holds the name.
+    Name(String),
+    /// An xml declaration or doctype has an invalid version, encoding or identifier.
+    Declaration,
+    /// A namespace prefix was bound to two namespaces on one element, or an attribute namespace had no prefix.
+    Namespace,
+    /// A start tag has the same attribute twice, with `Duplicates::Error`. Holds the name.
+    /// See `attr::Duplicates`.
+    DuplicateAttr(String),
+    /// Content was added to a void element like `<br>`, which has no end tag to hold it.
+    VoidContent,
+    /// A non-ascii character with `Renderer::with_ascii_only()`, where it can't be written
+    /// as a character reference, like in a comment or a script.
+    NonAscii(char),
+    /// A CDATA section in `Mode::Html`, which does not have them.
+    HtmlCdata,
+    /// Markup in an html element whose content is only text, like `<script>` or `<textarea>`,
+    /// where it would be text too, or end the element early.
+    TextOnly,
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind) -> Self {
+        Error {
+            kind,
+            path: Vec::new(),
+        }
+    }
+
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    ///
+    /// The tags of the elements that were open when the error happened, outermost first.
+    ///
+    pub fn path(&self) -> &[String] {
+        &self.path
+    }
+
+    ///
+    /// The underlying io error, if there was one.
+    ///
+    pub fn io_error(&self) -> Option<&std::io::Error> {
+        match &self.kind {
+            ErrorKind::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn with_path(mut self, path: Vec<String>) -> Self {
+        if self.path.is_empty() {
+            self.path = path;
+        }
+        self
+    }
+}
+
+impl From<fmt::Error> for Error {
+    fn from(_: fmt::Error) -> Self {
+        Error::new(ErrorKind::Fmt)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::new(ErrorKind::Io(e))
+    }
+}
+
+impl From<Error> for fmt::Error {
+    fn from(_: Error) -> Self {
+        fmt::Error
+    }
+}
+
+impl From<Error> for std::io::Error {
+    fn from(e: Error) -> Self {
+        match e.kind {
+            ErrorKind::Io(e) => e,
+            _ => std::io::Error::other(e),
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrorKind::Fmt => write!(f, "formatting error"),
+            ErrorKind::Io(e) => write!(f, "io error: {}", e),
+            ErrorKind::Escape => write!(f, "content would escape its context"),
+            ErrorKind::InvalidChar(c) => write!(f, "invalid character {:?}", c),
+            ErrorKind::Name(a) => write!(f, "invalid name {:?}", a),
+            ErrorKind::Declaration => write!(f, "invalid declaration"),
+            ErrorKind::Namespace => write!(f, "conflicting or invalid namespace"),
+            ErrorKind::DuplicateAttr(a) => write!(f, "duplicate attribute {:?}", a),
+            ErrorKind::VoidContent => write!(f, "content in a void element"),
+            ErrorKind::NonAscii(c) => {
+                write!(f, "non-ascii character {:?} that can't be escaped", c)
+            }
+            ErrorKind::HtmlCdata => write!(f, "CDATA section in html"),
+            ErrorKind::TextOnly => write!(f, "markup in an element that only holds text"),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if !self.path.is_empty() {
+            write!(f, " in ")?;
+            for a in self.path.iter() {
+                write!(f, "<{}>", a)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            ErrorKind::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,149 @@
+//!
+//! Classification of html5 elements and attributes used by `Mode::Html`.
+//!
+
+use std::fmt;
+
+///
+/// Elements that never have content or an end tag.
+///
+const VOID: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+///
+/// Elements whose content is not parsed as markup.
+///
+const RAW_TEXT: &[&str] = &["script", "style"];
+
+///
+/// Elements whose content is parsed as text, with character references.
+///
+const ESCAPABLE_RAW_TEXT: &[&str] = &["textarea", "title"];
+
+///
+/// Elements whose whitespace is significant.
+///
+const PRESERVE: &[&str] = &["listing", "pre", "textarea"];
+
+///
+/// Attributes whose presence alone means true.
+///
+const BOOLEAN: &[&str] = &[
+    "allowfullscreen",
+    "async",
+    "autofocus",
+    "autoplay",
+    "checked",
+    "controls",
+    "default",
+    "defer",
+    "disabled",
+    "formnovalidate",
+    "hidden",
+    "inert",
+    "ismap",
+    "itemscope",
+    "loop",
+    "multiple",
+    "muted",
+    "nomodule",
+    "novalidate",
+    "open",
+    "playsinline",
+    "readonly",
+    "required",
+    "reversed",
+    "selected",
+];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum TagKind {
+    Normal,
+    Void,
+    RawText(&'static str),
+    Preserve,
+}
+
+fn find(list: &[&'static str], name: &str) -> Option<&'static str> {
+    list.iter().copied().find(|a| a.eq_ignore_ascii_case(name))
+}
+
+pub(crate) fn tag_kind(tag: &str) -> TagKind {
+    if find(VOID, tag).is_some() {
+        TagKind::Void
+    } else if let Some(a) = find(RAW_TEXT, tag) {
+        TagKind::RawText(a)
+    } else if find(PRESERVE, tag).is_some() {
+        TagKind::Preserve
+    } else {
+        TagKind::Normal
+    }
+}
+
+///
+/// Whether the content of `tag` is only parsed as text, so that markup in it is text too.
+///
+pub(crate) fn text_only(tag: &str) -> bool {
+    find(RAW_TEXT, tag).is_some() || find(ESCAPABLE_RAW_TEXT, tag).is_some()
+}
+
+///
+/// Compares what is written to it with a list of names, ignoring ascii case,
+/// without holding on to it.
+///
+struct Matcher<'a> {
+    list: &'a [&'static str],
+    /// The names that still match, one bit each.
+    alive: u64,
+    len: usize,
+}
+
+impl fmt::Write for Matcher<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for b in s.bytes() {
+            for (i, a) in self.list.iter().enumerate() {
+                if !a
+                    .as_bytes()
+                    .get(self.len)
+                    .is_some_and(|a| a.eq_ignore_ascii_case(&b))
+                {
+                    self.alive &= !(1 << i);
+                }
+            }
+            self.len += 1;
+        }
+        // Stop formatting once nothing can match.
+        if self.alive == 0 {
+            return Err(fmt::Error);
+        }
+        Ok(())
+    }
+}
+
+///
+/// Find the name in `list` that `name` displays as, ignoring ascii case.
+///
+pub(crate) fn find_display(list: &[&'static str], name: &dyn fmt::Display) -> Option<&'static str> {
+    use fmt::Write;
+    debug_assert!(list.len() < 64);
+    let mut m = Matcher {
+        list,
+        alive: (1 << list.len()) - 1,
+        len: 0,
+    };
+    write!(m, "{}", name).ok()?;
+    let (_, a) = list
+        .iter()
+        .enumerate()
+        .find(|&(i, a)| m.alive & (1 << i) != 0 && a.len() == m.len)?;
+    Some(a)
+}
+
+///
+/// The boolean attribute that `name` displays as, if it is one.
+///
+pub(crate) fn boolean_attr(name: &dyn fmt::Display) -> Option<&'static str> {
+    find_display(BOOLEAN, name)
+}
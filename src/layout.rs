@@ -0,0 +1,440 @@
+//!
+//! Formatting to a line width, by holding back output until its layout is decided.
+//!
+//! The formatter in use still decides what the indentation and newlines look like.
+//! Its calls are recorded next to the output, and an element is written on one line
+//! if all of it fits in the width that is left. Lines that are too long are broken
+//! between their attributes instead.
+//!
+
+use super::*;
+use render::{Fmt, State};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+
+enum Token {
+    /// The indentation at the start of a line.
+    Start(String),
+    Text(String),
+    /// The separator before an attribute.
+    Sep(String),
+    /// The end of a line. Hard line ends can't be joined with the next line.
+    End {
+        newline: String,
+        hard: bool,
+    },
+    /// An element was opened after the line that ends before this.
+    Push,
+    /// An element is closed by the line that starts after this.
+    Pop,
+}
+
+fn width(s: &str) -> usize {
+    s.chars().count()
+}
+
+///
+/// How much output to write out.
+///
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Pass {
+    /// Only what is decided.
+    Ready,
+    /// What is decided, and the start of the first line as far as it can't change anymore.
+    Flush,
+    /// Everything, as no more output will follow.
+    Finish,
+}
+
+///
+/// What is known about the tokens from the front up to `next`, so that
+/// each token is only looked at once while its layout is undecided.
+///
+#[derive(Default)]
+struct Cursor {
+    /// The next token to look at.
+    next: usize,
+    /// The end of the first line.
+    end: Option<usize>,
+    /// The width of everything but the indentation.
+    used: usize,
+    /// How many elements are open after the first line.
+    depth: usize,
+    /// The element that the first line opens ends at this token, and fits on one line.
+    fits: Option<usize>,
+    /// The first line, or the element that it opens, can't be written on one line.
+    breaks: bool,
+}
+
+pub(crate) struct Layout {
+    width: usize,
+    tokens: VecDeque<Token>,
+    cursor: Cursor,
+    /// How many tokens at the front were written out already.
+    written: usize,
+    /// Whether a line is started and not ended yet.
+    in_line: bool,
+    /// Whether a line ended since the layout was last worked out.
+    ready: bool,
+}
+
+impl Layout {
+    pub(crate) fn new(width: usize) -> Self {
+        Layout {
+            width,
+            tokens: VecDeque::new(),
+            cursor: Cursor::default(),
+            written: 0,
+            in_line: false,
+            ready: false,
+        }
+    }
+
+    fn push(&mut self, token: Token) {
+        match token {
+            Token::Start(s) if self.in_line => self.text(&s),
+            Token::Start(_) => {
+                self.in_line = true;
+                self.tokens.push_back(token);
+            }
+            Token::Text(s) => self.text(&s),
+            Token::Sep(_) | Token::End { .. } if !self.in_line => {
+                self.push(Token::Start(String::new()));
+                self.push(token);
+            }
+            Token::End { .. } => {
+                self.in_line = false;
+                self.ready = true;
+                self.tokens.push_back(token);
+            }
+            Token::Pop => {
+                self.ready = true;
+                self.tokens.push_back(token);
+            }
+            Token::Sep(_) | Token::Push => self.tokens.push_back(token),
+        }
+    }
+
+    fn text(&mut self, s: &str) {
+        if s.is_empty() {
+            return;
+        }
+        if !self.in_line {
+            self.push(Token::Start(String::new()));
+        }
+        // Tokens that were looked at or written out already stay as they are.
+        let open = self.tokens.len() > self.cursor.next.max(self.written);
+        match self.tokens.back_mut() {
+            Some(Token::Text(a)) if open => a.push_str(s),
+            _ => self.tokens.push_back(Token::Text(s.to_string())),
+        }
+    }
+
+    ///
+    /// Write out as much as `pass` asks for.
+    ///
+    fn run(&mut self, out: &mut dyn tools::Sink, pass: Pass) -> fmt::Result {
+        if !self.ready && pass == Pass::Ready {
+            return Ok(());
+        }
+        self.ready = false;
+        loop {
+            while self.written == 0 && matches!(self.tokens.front(), Some(Token::Push | Token::Pop))
+            {
+                self.tokens.pop_front();
+                self.cursor = Cursor::default();
+            }
+            if self.tokens.is_empty() {
+                return Ok(());
+            }
+
+            self.scan();
+            let Some(end) = self.cursor.end else {
+                return match pass {
+                    Pass::Ready => Ok(()),
+                    Pass::Flush => self.write_fixed(out, None),
+                    Pass::Finish => self.line(out, self.tokens.len()),
+                };
+            };
+
+            match self.tokens.get(end + 1) {
+                // The line might be written on one line with the rest of the element it opens.
+                Some(Token::Push) | None if !self.cursor.breaks => match self.cursor.fits {
+                    Some(close) => self.joined(out, close + 1)?,
+                    None if pass == Pass::Finish => self.line(out, end + 1)?,
+                    None if pass == Pass::Flush => return self.write_fixed(out, Some(end)),
+                    None => return Ok(()),
+                },
+                _ => self.line(out, end + 1)?,
+            }
+        }
+    }
+
+    fn indent(&self) -> usize {
+        match self.tokens.front() {
+            Some(Token::Start(s)) => width(s),
+            _ => 0,
+        }
+    }
+
+    ///
+    /// Look at the tokens that came in since the last time, to find the end of the first line
+    /// and to work out if the element that it opens fits on one line.
+    ///
+    fn scan(&mut self) {
+        let room = self.width.saturating_sub(self.indent());
+        let c = &mut self.cursor;
+        while c.next < self.tokens.len() && (c.end.is_none() || !c.breaks && c.fits.is_none()) {
+            let i = c.next;
+            c.next += 1;
+            match &self.tokens[i] {
+                Token::Start(_) => {}
+                Token::Text(s) if s.contains('\n') => c.breaks = true,
+                Token::Text(s) | Token::Sep(s) => c.used += width(s),
+                Token::End { hard, .. } => {
+                    c.breaks |= *hard;
+                    match c.end {
+                        None => c.end = Some(i),
+                        Some(_) if c.depth == 0 => c.fits = Some(i),
+                        Some(_) => {}
+                    }
+                }
+                Token::Push => c.depth += 1,
+                Token::Pop => c.depth = c.depth.saturating_sub(1),
+            }
+            c.breaks |= c.used > room;
+        }
+    }
+
+    ///
+    /// Write the part of the first line that is the same however it is laid out.
+    /// That is all of it but its end, once the end is known, and otherwise up to the second
+    /// attribute, which could still be moved to a line of its own.
+    ///
+    fn write_fixed(&mut self, out: &mut dyn tools::Sink, end: Option<usize>) -> fmt::Result {
+        let upto = end.unwrap_or_else(|| {
+            let mut seps = self
+                .tokens
+                .iter()
+                .enumerate()
+                .filter(|(_, a)| matches!(a, Token::Sep(_)))
+                .map(|(i, _)| i);
+            seps.nth(1).unwrap_or(self.tokens.len())
+        });
+        for a in self.tokens.range(self.written.min(upto)..upto) {
+            if let Token::Start(s) | Token::Text(s) | Token::Sep(s) = a {
+                out.write_str(s)?;
+            }
+        }
+        self.written = self.written.max(upto);
+        Ok(())
+    }
+
+    ///
+    /// Remove the first `n` tokens, which are about to be written.
+    ///
+    fn take(&mut self, n: usize) -> (usize, std::collections::vec_deque::Drain<'_, Token>) {
+        let written = std::mem::take(&mut self.written);
+        self.cursor = Cursor::default();
+        (written, self.tokens.drain(..n))
+    }
+
+    ///
+    /// Write the first `n` tokens on one line.
+    ///
+    fn joined(&mut self, out: &mut dyn tools::Sink, n: usize) -> fmt::Result {
+        let (written, tokens) = self.take(n);
+        for (i, a) in tokens.enumerate().skip(written) {
+            match a {
+                Token::Start(s) if i == 0 => out.write_str(&s)?,
+                Token::Text(s) | Token::Sep(s) => out.write_str(&s)?,
+                Token::End { newline, .. } if i == n - 1 => out.write_str(&newline)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    ///
+    /// Write the line made of the first `n` tokens, breaking it
+    /// between attributes if it is too long.
+    ///
+    fn line(&mut self, out: &mut dyn tools::Sink, n: usize) -> fmt::Result {
+        let used: usize = self
+            .tokens
+            .iter()
+            .take(n)
+            .map(|a| match a {
+                Token::Start(s) | Token::Text(s) | Token::Sep(s) => width(s),
+                _ => 0,
+            })
+            .sum();
+        let wrap = used > self.width;
+
+        let mut indent = String::new();
+        let mut newline = "\n".to_string();
+        if let Some(Token::End { newline: a, .. }) = self.tokens.get(n - 1) {
+            newline.clone_from(a);
+        }
+        let mut first = true;
+        let (written, tokens) = self.take(n);
+        for (i, a) in tokens.enumerate() {
+            // Written out already, but still needed to work out the indentation.
+            let write = i >= written;
+            match a {
+                Token::Start(s) => {
+                    if write {
+                        out.write_str(&s)?;
+                    }
+                    indent = s;
+                }
+                Token::Text(s) => {
+                    if write {
+                        out.write_str(&s)?;
+                    }
+                    if first {
+                        indent.push_str(&" ".repeat(width(&s)));
+                    }
+                }
+                Token::Sep(s) if first || !wrap => {
+                    if write {
+                        out.write_str(&s)?;
+                    }
+                    if first {
+                        indent.push_str(&" ".repeat(width(&s)));
+                    }
+                    first = false;
+                }
+                Token::Sep(_) => {
+                    out.write_str(&newline)?;
+                    out.write_str(&indent)?;
+                }
+                Token::End { newline, .. } => out.write_str(&newline)?,
+                Token::Push | Token::Pop => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+///
+/// Records the calls of the formatter in use.
+///
+struct LayoutFmt<'a> {
+    inner: &'a mut dyn Fmt,
+    layout: &'a RefCell<Layout>,
+}
+
+impl LayoutFmt<'_> {
+    fn record(
+        &mut self,
+        func: impl FnOnce(&mut dyn Fmt, &mut String) -> fmt::Result,
+        token: impl FnOnce(String) -> Token,
+    ) -> fmt::Result {
+        let inline = self.inner.is_inline_mode();
+        let mut s = String::new();
+        func(self.inner, &mut s)?;
+        let token = if inline { Token::Text(s) } else { token(s) };
+        self.layout.borrow_mut().push(token);
+        Ok(())
+    }
+}
+
+impl Fmt for LayoutFmt<'_> {
+    fn push(&mut self) {
+        if !self.inner.is_inline_mode() {
+            self.layout.borrow_mut().push(Token::Push);
+        }
+        self.inner.push()
+    }
+    fn pop(&mut self) {
+        if !self.inner.is_inline_mode() {
+            self.layout.borrow_mut().push(Token::Pop);
+        }
+        self.inner.pop()
+    }
+    fn tabs(&mut self, _: &mut dyn fmt::Write) -> fmt::Result {
+        self.record(|f, w| f.tabs(w), Token::Start)
+    }
+    fn end_tag(&mut self, _: &mut dyn fmt::Write) -> fmt::Result {
+        self.record(
+            |f, w| f.end_tag(w),
+            |newline| Token::End {
+                newline,
+                hard: false,
+            },
+        )
+    }
+    fn line_break(&mut self, _: &mut dyn fmt::Write) -> fmt::Result {
+        self.record(
+            |f, w| f.line_break(w),
+            |newline| Token::End {
+                newline,
+                hard: true,
+            },
+        )
+    }
+    fn set_inline_mode(&mut self, val: bool) {
+        self.inner.set_inline_mode(val)
+    }
+    fn is_inline_mode(&mut self) -> bool {
+        self.inner.is_inline_mode()
+    }
+    fn swap_tab(&mut self, tab: &'static str) -> &'static str {
+        self.inner.swap_tab(tab)
+    }
+    fn attr_sep(&mut self, _: &mut dyn fmt::Write) -> fmt::Result {
+        self.record(|f, w| f.attr_sep(w), Token::Sep)
+    }
+}
+
+///
+/// Holds on to the output until its layout is decided.
+///
+struct LayoutSink<'a> {
+    out: &'a mut dyn tools::Sink,
+    layout: &'a RefCell<Layout>,
+}
+
+impl fmt::Write for LayoutSink<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut layout = self.layout.borrow_mut();
+        layout.run(self.out, Pass::Ready)?;
+        layout.text(s);
+        Ok(())
+    }
+}
+
+impl tools::Sink for LayoutSink<'_> {
+    fn boundary(&mut self) -> fmt::Result {
+        self.layout.borrow_mut().run(self.out, Pass::Ready)?;
+        self.out.boundary()
+    }
+    fn flush(&mut self) -> fmt::Result {
+        self.layout.borrow_mut().run(self.out, Pass::Flush)?;
+        self.out.flush()
+    }
+}
+
+///
+/// Call `func` with a writer whose output goes through `layout` to `sink`.
+///
+pub(crate) fn with<R>(
+    layout: &RefCell<Layout>,
+    fmt: &mut dyn Fmt,
+    state: &mut State,
+    sink: &mut dyn tools::Sink,
+    func: impl FnOnce(ElemWrite) -> Result<R, Error>,
+) -> Result<R, Error> {
+    let mut fmt = LayoutFmt { inner: fmt, layout };
+    let mut out = LayoutSink { out: sink, layout };
+    func(ElemWrite(SinkWrap(&mut out), &mut fmt, state))
+}
+
+///
+/// Write out everything that is still held back.
+///
+pub(crate) fn finish(layout: &RefCell<Layout>, sink: &mut dyn tools::Sink) -> Result<(), Error> {
+    layout.borrow_mut().run(sink, Pass::Finish)?;
+    Ok(())
+}
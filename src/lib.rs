@@ -8,11 +8,17 @@ use std::fmt;
 pub mod attr;
 pub mod build;
 pub mod elem;
+mod error;
+pub use error::{Error, ErrorKind};
+mod html;
+mod layout;
+pub mod ns;
 use attr::*;
-mod render;
+pub mod render;
 pub mod stack;
+mod stream;
 use elem::*;
-use tools::WriteWrap;
+use tools::{SinkWrap, WriteWrap};
 
 pub mod prelude {
     //! The tagu prelude
@@ -24,25 +30,263 @@ pub mod prelude {
 
 pub mod util {
     use super::*;
+    ///
+    /// Create a comment. Same as `build::comment()`.
+    ///
     pub fn comment(a: impl fmt::Display) -> impl Elem + Locked {
-        build::single(a).with_start("!--").with_ending("--")
+        build::comment(a)
     }
 }
 
 ///
 /// Render elements to a writer
 ///
-pub fn render<E: Elem + Locked, W: fmt::Write>(elem: E, writer: W) -> fmt::Result {
+pub fn render<E: Elem + Locked, W: fmt::Write>(elem: E, writer: W) -> Result<(), Error> {
     render::Renderer::new().render(elem, writer)
 }
 
 ///
 /// Render elements to a writer that allows for escaping elements.
 ///
-pub fn render_escapable<E: Elem, W: fmt::Write>(elem: E, writer: W) -> fmt::Result {
+pub fn render_escapable<E: Elem, W: fmt::Write>(elem: E, writer: W) -> Result<(), Error> {
     render::Renderer::new().render_escapable(elem, writer)
 }
 
+///
+/// Render elements to a `std::io::Write`.
+///
+/// Output is buffered internally, and if the writer fails, its error is returned directly.
+///
+/// ```
+/// use tagu::build;
+/// use tagu::prelude::*;
+/// let mut v = Vec::new();
+/// tagu::render_io(build::elem("a").inline(), &mut v).unwrap();
+/// assert_eq!(v, b"<a></a>\n");
+///
+/// struct Broken;
+/// impl std::io::Write for Broken {
+///     fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+///         Err(std::io::ErrorKind::BrokenPipe.into())
+///     }
+///     fn flush(&mut self) -> std::io::Result<()> {
+///         Ok(())
+///     }
+/// }
+/// let e = tagu::render_io(build::elem("a"), Broken).unwrap_err();
+/// assert_eq!(e.kind(), std::io::ErrorKind::BrokenPipe);
+/// ```
+///
+pub fn render_io<E: Elem + Locked, W: std::io::Write>(elem: E, writer: W) -> std::io::Result<()> {
+    render::Renderer::new().render_io(elem, writer)
+}
+
+///
+/// Render elements to a `std::io::Write` that allows for escaping elements.
+///
+pub fn render_io_escapable<E: Elem, W: std::io::Write>(elem: E, writer: W) -> std::io::Result<()> {
+    render::Renderer::new().render_io_escapable(elem, writer)
+}
+
+///
+/// Render elements to a new string.
+///
+/// ```
+/// use tagu::build;
+/// let s = tagu::render_to_string(build::single("a")).unwrap();
+/// assert_eq!(s, "<a/>\n");
+/// ```
+///
+pub fn render_to_string<E: Elem + Locked>(elem: E) -> Result<String, Error> {
+    render::Renderer::new().render_to_string(elem)
+}
+
+///
+/// Render an element with children appended to it into chunks of at most `chunk_size` bytes,
+/// pulled from an iterator. Children are rendered as the chunks are pulled, on the calling thread.
+///
+/// See `render::Renderer::render_chunks()`.
+///
+/// ```
+/// use tagu::build;
+/// use tagu::prelude::*;
+/// let rows = (0..100).map(|i| build::elem("row").inline().append(build::raw(i)));
+///
+/// let mut s = String::new();
+/// for chunk in tagu::render_chunks(build::elem("table"), rows, 64) {
+///     let chunk = chunk.unwrap();
+///     assert!(chunk.len() <= 64);
+///     s.push_str(&chunk);
+/// }
+/// assert!(s.starts_with("<table>\n\t<row>0</row>\n"));
+/// assert!(s.ends_with("\t<row>99</row>\n</table>\n"));
+/// ```
+///
+pub fn render_chunks<E: Elem + Locked, I: IntoIterator>(
+    elem: E,
+    children: I,
+    chunk_size: usize,
+) -> render::Chunks<render::PrettyFmt, E, I::IntoIter>
+where
+    I::Item: Elem + Locked,
+{
+    render::Renderer::new().render_chunks(elem, children, chunk_size)
+}
+
+///
+/// Render an element with children appended to it to a `futures_io::AsyncWrite`.
+/// Requires the `async` feature.
+///
+/// See `render::Renderer::render_async()`.
+///
+/// ```
+/// use tagu::build;
+/// use tagu::prelude::*;
+/// let children = [build::elem("b").inline()];
+/// let mut v = Vec::new();
+/// futures_executor::block_on(tagu::render_async(build::elem("a"), children, &mut v)).unwrap();
+/// assert_eq!(v, b"<a>\n\t<b></b>\n</a>\n");
+/// ```
+///
+#[cfg(feature = "async")]
+pub async fn render_async<E: Elem + Locked, I: IntoIterator>(
+    elem: E,
+    children: I,
+    writer: impl futures_io::AsyncWrite + Unpin,
+) -> std::io::Result<()>
+where
+    I::Item: Elem + Locked,
+{
+    render::Renderer::new()
+        .render_async(elem, children, writer)
+        .await
+}
+
+///
+/// Render an element with children appended to it to a `tokio::io::AsyncWrite`.
+/// Requires the `tokio` feature.
+///
+/// See `render::Renderer::render_tokio()`.
+///
+/// ```
+/// use tagu::build;
+/// use tagu::prelude::*;
+/// use tokio::io::AsyncReadExt;
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() {
+///     let rows = (0..1000).map(|i| build::elem("row").inline().append(build::raw(i)));
+///
+///     let (w, mut r) = tokio::io::duplex(64);
+///     let task = tokio::spawn(tagu::render_tokio(build::elem("table"), rows, w));
+///
+///     let mut s = String::new();
+///     r.read_to_string(&mut s).await.unwrap();
+///     task.await.unwrap().unwrap();
+///
+///     assert!(s.starts_with("<table>\n\t<row>0</row>\n"));
+///     assert!(s.ends_with("\t<row>999</row>\n</table>\n"));
+/// }
+/// ```
+///
+#[cfg(feature = "tokio")]
+pub async fn render_tokio<E: Elem + Locked, I: IntoIterator>(
+    elem: E,
+    children: I,
+    writer: impl tokio::io::AsyncWrite + Unpin,
+) -> std::io::Result<()>
+where
+    I::Item: Elem + Locked,
+{
+    render::Renderer::new()
+        .render_tokio(elem, children, writer)
+        .await
+}
+
+///
+/// Render elements to a writer as html5.
+///
+/// ```
+/// use tagu::build;
+/// use tagu::prelude::*;
+/// let k = build::elem("div").append(elems!(
+///     build::single("input").with(attrs!(("type", "checkbox"), ("checked", ""))),
+///     build::elem("script").inline().append(build::raw("if (a < b) {}"))
+/// ));
+/// let mut s = String::new();
+/// tagu::render_html(k, &mut s).unwrap();
+/// assert_eq!(
+///     s,
+///     "<div>\n\t<input type=\"checkbox\" checked>\n\t<script>if (a < b) {}</script>\n</div>\n"
+/// );
+///
+/// let k = build::elem("script").append(build::raw("</script>"));
+/// assert!(tagu::render_html(k, &mut String::new()).is_err());
+///
+/// let k = build::elem("script").append(elems!(build::raw("</Scr"), build::raw("iPt")));
+/// assert!(tagu::render_html(k, &mut String::new()).is_err());
+///
+/// // The start tag in between ends the first script, so nothing is split.
+/// let k = elems!(
+///     build::elem("script").inline().append(build::raw("</scr")),
+///     build::elem("script").inline().append(build::raw("ipt"))
+/// );
+/// let mut s = String::new();
+/// tagu::render_html(k, &mut s).unwrap();
+/// assert_eq!(s, "<script></scr</script>\n<script>ipt</script>\n");
+///
+/// // Split across nodes.
+/// let k = build::elem("script")
+///     .inline()
+///     .append(build::raw("</scr"))
+///     .append(build::raw("ipt><img src=x onerror=alert(1)>"));
+/// assert!(tagu::render_html(k, &mut String::new()).is_err());
+///
+/// let k = build::elem("br").append(build::elem("p"));
+/// let e = tagu::render_html(k, &mut String::new()).unwrap_err();
+/// assert!(matches!(e.kind(), tagu::ErrorKind::VoidContent));
+///
+/// // Elements like `<script>` and `<textarea>` only hold text, so markup in them fails.
+/// let x = "</script><img src=x onerror=alert(1)>";
+/// let e = tagu::render_html(build::elem("script").append(build::comment(x)), &mut String::new());
+/// assert!(matches!(e.unwrap_err().kind(), tagu::ErrorKind::TextOnly));
+///
+/// let k = build::elem("script").append(build::processing_instruction("a", x));
+/// assert!(tagu::render_html(k, &mut String::new()).is_err());
+///
+/// let k = build::elem("script").append(build::doctype("html").with_system(x));
+/// assert!(tagu::render_html(k, &mut String::new()).is_err());
+///
+/// let x = "</textarea><img src=x onerror=alert(1)>";
+/// let k = build::elem("textarea").append(build::comment(x));
+/// assert!(tagu::render_html(k, &mut String::new()).is_err());
+///
+/// // A raw text element in another one can't take over the check for the end of it.
+/// let x = "</style><img src=x onerror=alert(1)>";
+/// let k = build::elem("style").append(build::elem("script").append(x));
+/// assert!(tagu::render_html(k, &mut String::new()).is_err());
+/// ```
+///
+pub fn render_html<E: Elem + Locked, W: fmt::Write>(elem: E, writer: W) -> Result<(), Error> {
+    render::Renderer::new()
+        .with_mode(render::Mode::Html)
+        .render(elem, writer)
+}
+
+///
+/// Render elements to a writer without any formatting whitespace.
+///
+pub fn render_compact<E: Elem + Locked, W: fmt::Write>(elem: E, writer: W) -> Result<(), Error> {
+    render::Renderer::compact().render(elem, writer)
+}
+
+///
+/// Render elements to a writer that allows for escaping elements, without any formatting whitespace.
+///
+pub fn render_compact_escapable<E: Elem, W: fmt::Write>(elem: E, writer: W) -> Result<(), Error> {
+    render::Renderer::compact().render_escapable(elem, writer)
+}
+
 ///
 /// An std out that implements fmt::Write
 ///
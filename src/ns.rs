@@ -0,0 +1,130 @@
+//!
+//! Xml namespaces
+//!
+//! Elements and attributes built with `build::elem_ns()`, `build::single_ns()` and `build::attr_ns()`
+//! belong to a namespace. While rendering, the namespaces that are in scope are tracked,
+//! and a `xmlns` or `xmlns:prefix` declaration is only written where one is not in scope already.
+//!
+//! Elements built with `build::elem()` are in whatever default namespace is in scope.
+//! Declarations written by hand, like `("xmlns", "...")`, are not tracked.
+//!
+
+///
+/// A namespace, and the prefix its elements and attributes are written with.
+///
+/// Without a prefix, elements are written unprefixed and the namespace is declared
+/// as the default namespace. Attributes always need a prefix.
+///
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Namespace {
+    prefix: Option<&'static str>,
+    uri: &'static str,
+}
+
+impl Namespace {
+    ///
+    /// A namespace that is declared as the default namespace.
+    ///
+    pub const fn new(uri: &'static str) -> Self {
+        Namespace { prefix: None, uri }
+    }
+
+    ///
+    /// A namespace that is written with a prefix.
+    ///
+    pub const fn prefixed(prefix: &'static str, uri: &'static str) -> Self {
+        Namespace {
+            prefix: Some(prefix),
+            uri,
+        }
+    }
+
+    ///
+    /// The same namespace written with a different prefix.
+    ///
+    pub const fn with_prefix(self, prefix: &'static str) -> Self {
+        Namespace::prefixed(prefix, self.uri)
+    }
+
+    pub const fn prefix(&self) -> Option<&'static str> {
+        self.prefix
+    }
+
+    pub const fn uri(&self) -> &'static str {
+        self.uri
+    }
+}
+
+pub const SVG: Namespace = Namespace::new("http://www.w3.org/2000/svg");
+pub const XHTML: Namespace = Namespace::new("http://www.w3.org/1999/xhtml");
+pub const MATHML: Namespace = Namespace::new("http://www.w3.org/1998/Math/MathML");
+pub const XLINK: Namespace = Namespace::prefixed("xlink", "http://www.w3.org/1999/xlink");
+pub const INKSCAPE: Namespace =
+    Namespace::prefixed("inkscape", "http://www.inkscape.org/namespaces/inkscape");
+
+///
+/// Bound to the `xml` prefix everywhere, so it is never declared.
+///
+pub const XML: Namespace = Namespace::prefixed("xml", "http://www.w3.org/XML/1998/namespace");
+
+///
+/// The bindings in scope at some point of a render.
+///
+pub(crate) struct Scopes {
+    bindings: Vec<Namespace>,
+    /// Where the bindings of the innermost element start.
+    start: usize,
+}
+
+///
+/// What to restore once an element is closed.
+///
+#[derive(Copy, Clone)]
+pub(crate) struct Mark {
+    len: usize,
+    start: usize,
+}
+
+impl Scopes {
+    pub(crate) fn new() -> Self {
+        Scopes {
+            bindings: vec![XML],
+            start: 1,
+        }
+    }
+
+    ///
+    /// Start the scope of a new element.
+    ///
+    pub(crate) fn open(&mut self) -> Mark {
+        let mark = Mark {
+            len: self.bindings.len(),
+            start: self.start,
+        };
+        self.start = self.bindings.len();
+        mark
+    }
+
+    pub(crate) fn close(&mut self, mark: Mark) {
+        self.bindings.truncate(mark.len);
+        self.start = mark.start;
+    }
+
+    ///
+    /// Bring a namespace into scope. Returns whether it has to be declared,
+    /// or an error if its prefix is already bound to something else on the same element.
+    ///
+    pub(crate) fn bind(&mut self, ns: Namespace) -> Result<bool, crate::Error> {
+        let found = self.bindings.iter().rposition(|a| a.prefix == ns.prefix);
+        match found {
+            Some(i) if self.bindings[i].uri == ns.uri => Ok(false),
+            Some(i) if i >= self.start || ns.prefix == XML.prefix => {
+                Err(crate::Error::new(crate::ErrorKind::Namespace))
+            }
+            _ => {
+                self.bindings.push(ns);
+                Ok(true)
+            }
+        }
+    }
+}
@@ -3,14 +3,47 @@
 //!
 
 use super::*;
+use crate::layout::Layout;
+pub use crate::stream::Chunks;
+use std::cell::RefCell;
 
-pub struct Renderer {
-    fmt: PrettyFmt,
+///
+/// Renders elements using a particular formatter.
+///
+/// ```
+/// use tagu::build;
+/// use tagu::prelude::*;
+/// use tagu::render::{PrettyFmt, Renderer};
+/// let mut s = String::new();
+/// let k = build::elem("a").append(build::single("b"));
+/// Renderer::new()
+///     .with_fmt(PrettyFmt::new().with_tab("  "))
+///     .render(k, &mut s)
+///     .unwrap();
+/// assert_eq!(s, "<a>\n  <b/>\n</a>\n");
+/// ```
+///
+pub struct Renderer<F = PrettyFmt> {
+    fmt: F,
+    mode: Mode,
+    auto_flush: bool,
+    invalid_chars: tools::InvalidChars,
+    minimal: bool,
+    ascii: bool,
+    width: Option<usize>,
+    duplicates: attr::Duplicates,
 }
 impl Renderer {
     pub fn new() -> Self {
         Renderer {
             fmt: PrettyFmt::new(),
+            mode: Mode::Xml,
+            auto_flush: false,
+            invalid_chars: tools::InvalidChars::Replace,
+            minimal: false,
+            ascii: false,
+            width: None,
+            duplicates: attr::Duplicates::Allow,
         }
     }
 }
@@ -21,40 +54,640 @@ impl Default for Renderer {
     }
 }
 
-impl Renderer {
-    // pub fn with_fmt<K: Fmt>(self, a: K) -> Renderer<K> {
-    //     Renderer { fmt: a }
-    // }
+impl Renderer<NoFmt> {
+    ///
+    /// A renderer that writes no formatting whitespace.
+    ///
+    pub fn compact() -> Self {
+        Renderer {
+            fmt: NoFmt,
+            mode: Mode::Xml,
+            auto_flush: false,
+            invalid_chars: tools::InvalidChars::Replace,
+            minimal: false,
+            ascii: false,
+            width: None,
+            duplicates: attr::Duplicates::Allow,
+        }
+    }
+}
+
+impl<F: Fmt> Renderer<F> {
+    ///
+    /// Use a different formatter.
+    ///
+    pub fn with_fmt<K: Fmt>(self, a: K) -> Renderer<K> {
+        Renderer {
+            fmt: a,
+            mode: self.mode,
+            auto_flush: self.auto_flush,
+            invalid_chars: self.invalid_chars,
+            minimal: self.minimal,
+            ascii: self.ascii,
+            width: self.width,
+            duplicates: self.duplicates,
+        }
+    }
+
+    ///
+    /// Set the serialization mode.
+    ///
+    pub fn with_mode(self, mode: Mode) -> Self {
+        Renderer { mode, ..self }
+    }
+
+    ///
+    /// Flush the sink after each child of the outermost element, and after the outermost element itself.
+    ///
+    /// ```
+    /// use tagu::build;
+    /// use tagu::prelude::*;
+    /// let rows = (0..3).map(|i| build::elem("row").inline().append(build::raw(i)));
+    /// let chunks: Result<Vec<_>, _> = tagu::render::Renderer::new()
+    ///     .with_auto_flush(true)
+    ///     .render_chunks(build::elem("table"), rows, 1024)
+    ///     .collect();
+    /// assert_eq!(
+    ///     chunks.unwrap(),
+    ///     ["<table>\n\t<row>0</row>\n", "\t<row>1</row>\n", "\t<row>2</row>\n", "</table>\n"]
+    /// );
+    /// ```
+    ///
+    pub fn with_auto_flush(self, auto_flush: bool) -> Self {
+        Renderer { auto_flush, ..self }
+    }
+
+    ///
+    /// Set what happens to characters in text and attribute values that xml does not allow.
+    /// They are replaced with `\u{FFFD}` by default.
+    ///
+    /// ```
+    /// use tagu::build;
+    /// use tagu::prelude::*;
+    /// use tagu::render::Renderer;
+    /// use tagu::tools::InvalidChars;
+    /// let k = || build::elem("a").with(("b", "\u{1B}[0m")).inline().append(build::raw("x\u{0}y"));
+    ///
+    /// let mut s = String::new();
+    /// Renderer::new().render(k(), &mut s).unwrap();
+    /// assert_eq!(s, "<a b=\"\u{FFFD}[0m\">x\u{FFFD}y</a>\n");
+    ///
+    /// let mut s = String::new();
+    /// Renderer::new().with_invalid_chars(InvalidChars::Strip).render(k(), &mut s).unwrap();
+    /// assert_eq!(s, "<a b=\"[0m\">xy</a>\n");
+    ///
+    /// let e = Renderer::new()
+    ///     .with_invalid_chars(InvalidChars::Error)
+    ///     .render(k(), &mut String::new())
+    ///     .unwrap_err();
+    /// assert!(matches!(e.kind(), tagu::ErrorKind::InvalidChar('\u{1B}')));
+    /// ```
+    ///
+    pub fn with_invalid_chars(self, invalid_chars: tools::InvalidChars) -> Self {
+        Renderer {
+            invalid_chars,
+            ..self
+        }
+    }
+
+    ///
+    /// Only escape the characters that the mode requires, see `tools::Escaping`.
+    ///
+    /// ```
+    /// use tagu::build;
+    /// use tagu::prelude::*;
+    /// use tagu::render::{Mode, Renderer};
+    /// let k = || build::elem("a").with(("b", "'<>'")).inline().append(build::raw("\"1 > 0\""));
+    ///
+    /// let mut s = String::new();
+    /// Renderer::new().with_minimal_escaping(true).render(k(), &mut s).unwrap();
+    /// assert_eq!(s, "<a b=\"'&lt;>'\">\"1 > 0\"</a>\n");
+    ///
+    /// let mut s = String::new();
+    /// Renderer::new().with_mode(Mode::Html).with_minimal_escaping(true).render(k(), &mut s).unwrap();
+    /// assert_eq!(s, "<a b=\"'<>'\">\"1 > 0\"</a>\n");
+    ///
+    /// // `]]>` is not allowed in xml text, even split across nodes.
+    /// let k = || build::elem("a").inline().append(elems!("x]", "]", ">y"));
+    /// let mut s = String::new();
+    /// Renderer::new().with_minimal_escaping(true).render(k(), &mut s).unwrap();
+    /// assert_eq!(s, "<a>x]]&gt;y</a>\n");
+    ///
+    /// // Html text has no CDATA sections to end.
+    /// let mut s = String::new();
+    /// Renderer::new().with_mode(Mode::Html).with_minimal_escaping(true).render(k(), &mut s).unwrap();
+    /// assert_eq!(s, "<a>x]]>y</a>\n");
+    ///
+    /// // Nor is it in xml raw text, where `<` is escaped as well.
+    /// let k = build::elem("script").inline().append(build::raw("]]")).append(build::raw("></scr"));
+    /// let mut s = String::new();
+    /// Renderer::new().with_minimal_escaping(true).render(k, &mut s).unwrap();
+    /// assert_eq!(s, "<script>]]&gt;&lt;/scr</script>\n");
+    ///
+    /// // Html attribute values keep their `<`, so start tags can't go where they could end
+    /// // the element around them.
+    /// let x = "</script><img src=x onerror=alert(1)>";
+    /// let html = || Renderer::new().with_mode(Mode::Html).with_minimal_escaping(true);
+    /// let k = build::elem("script").append(build::single("a").with(("x", x)));
+    /// assert!(html().render(k, &mut String::new()).is_err());
+    ///
+    /// let k = build::elem("script").append(build::single("a").with(tagu::attr::Classes::new().with(x)));
+    /// assert!(html().render(k, &mut String::new()).is_err());
+    ///
+    /// let k = build::elem("script").append(build::single("a").with(("style", format!("content: '{}'", x))));
+    /// assert!(html().render(k, &mut String::new()).is_err());
+    /// ```
+    ///
+    pub fn with_minimal_escaping(self, minimal: bool) -> Self {
+        Renderer { minimal, ..self }
+    }
+
+    ///
+    /// Write every non-ascii character in text and attribute values as a numeric character reference.
+    ///
+    /// Tag and attribute names, comments and html raw text cannot contain references,
+    /// so non-ascii characters in them fail the render.
+    ///
+    /// ```
+    /// use tagu::build;
+    /// use tagu::prelude::*;
+    /// use tagu::render::Renderer;
+    /// let k = build::elem("p").with(("title", "café")).inline().append(build::raw("1 € ≈ 1 $"));
+    /// let mut s = String::new();
+    /// Renderer::new().with_ascii_only(true).render(k, &mut s).unwrap();
+    /// assert_eq!(s, "<p title=\"caf&#xE9;\">1 &#x20AC; &#x2248; 1 $</p>\n");
+    ///
+    /// let k = build::single("café");
+    /// assert!(Renderer::new().with_ascii_only(true).render(k, &mut String::new()).is_err());
+    ///
+    /// let k = build::comment("café");
+    /// let e = Renderer::new().with_ascii_only(true).render(k, &mut String::new()).unwrap_err();
+    /// assert!(matches!(e.kind(), tagu::ErrorKind::NonAscii('é')));
+    /// ```
+    ///
+    pub fn with_ascii_only(self, ascii: bool) -> Self {
+        Renderer { ascii, ..self }
+    }
+
+    ///
+    /// Set what happens when a start tag has the same attribute more than once.
+    /// All of them are written by default. See `attr::Duplicates`.
+    ///
+    pub fn with_duplicates(self, duplicates: attr::Duplicates) -> Self {
+        Renderer { duplicates, ..self }
+    }
+
+    ///
+    /// Lay out the output to fit in a line width, counted in chars.
+    ///
+    /// Elements that fit in the width that is left after their indentation are written
+    /// on one line. Lines that don't fit are broken between their attributes, which are
+    /// lined up with the first one. Output is held back until its layout is decided.
+    ///
+    /// ```
+    /// use tagu::build;
+    /// use tagu::prelude::*;
+    /// use tagu::render::{PrettyFmt, Renderer};
+    /// let rect = build::single("rect").with(attrs!(
+    ///     ("x", 0),
+    ///     ("y", 0),
+    ///     ("width", 100),
+    ///     ("height", 50),
+    ///     ("style", "fill:blue")
+    /// ));
+    /// let text = build::elem("text").append(build::elem("tspan").with(("x", 5)));
+    /// let k = build::elem("svg").append(rect).append(text);
+    /// let mut s = String::new();
+    /// Renderer::new()
+    ///     .with_fmt(PrettyFmt::new().with_tab("  "))
+    ///     .with_line_width(40)
+    ///     .render(k, &mut s)
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     s,
+    ///     "\
+    /// <svg>
+    ///   <rect x=\"0\"
+    ///         y=\"0\"
+    ///         width=\"100\"
+    ///         height=\"50\"
+    ///         style=\"fill:blue\"/>
+    ///   <text><tspan x=\"5\"></tspan></text>
+    /// </svg>
+    /// "
+    /// );
+    /// ```
+    ///
+    /// A flush writes out all that can't be laid out differently anymore, which is
+    /// the start of an element whose layout still depends on what comes after it.
+    ///
+    /// ```
+    /// use tagu::build;
+    /// use tagu::prelude::*;
+    /// let rows = (0..3).map(|i| build::elem("row").inline().append(build::raw(i)));
+    /// let chunks: Result<Vec<_>, _> = tagu::render::Renderer::new()
+    ///     .with_line_width(80)
+    ///     .with_auto_flush(true)
+    ///     .render_chunks(build::elem("table"), rows, 1024)
+    ///     .collect();
+    /// assert_eq!(
+    ///     chunks.unwrap(),
+    ///     ["<table>", "<row>0</row><row>1</row><row>2</row></table>\n"]
+    /// );
+    /// ```
+    ///
+    pub fn with_line_width(self, width: usize) -> Self {
+        Renderer {
+            width: Some(width),
+            ..self
+        }
+    }
+
     pub fn render<E: Elem + Locked, W: fmt::Write>(
         &mut self,
         elem: E,
-        mut writer: W,
-    ) -> fmt::Result {
-        ElemWrite(WriteWrap(&mut writer), &mut self.fmt).render_inner(elem)
+        writer: W,
+    ) -> Result<(), Error> {
+        self.render_dyn(elem, &mut tools::Plain(writer))
     }
     pub fn render_escapable<E: Elem, W: fmt::Write>(
         &mut self,
         elem: E,
-        mut writer: W,
-    ) -> fmt::Result {
-        let mut e = ElemWrite(WriteWrap(&mut writer), &mut self.fmt);
-        let tail = elem.render_head(e.borrow_mut2())?;
-        tail.render(e)
+        writer: W,
+    ) -> Result<(), Error> {
+        self.render_dyn(elem, &mut tools::Plain(writer))
+    }
+
+    ///
+    /// Render to a `tools::Sink`, which gets told about element boundaries and flushes.
+    ///
+    pub fn render_sink<E: Elem + Locked, S: tools::Sink>(
+        &mut self,
+        elem: E,
+        mut sink: S,
+    ) -> Result<(), Error> {
+        self.render_dyn(elem, &mut sink)
+    }
+
+    ///
+    /// Render to a `tools::Sink`, allowing for escaping elements.
+    ///
+    pub fn render_sink_escapable<E: Elem, S: tools::Sink>(
+        &mut self,
+        elem: E,
+        mut sink: S,
+    ) -> Result<(), Error> {
+        self.render_dyn(elem, &mut sink)
+    }
+
+    pub(crate) fn render_dyn<E: Elem>(
+        &mut self,
+        elem: E,
+        sink: &mut dyn tools::Sink,
+    ) -> Result<(), Error> {
+        let mut state = self.state();
+        let layout = self.layout();
+        let res = self
+            .with_writer(&mut state, layout.as_ref(), sink, |mut w| {
+                w.render_inner(elem)
+            })
+            .and_then(|_| match &layout {
+                Some(layout) => crate::layout::finish(layout, sink),
+                None => Ok(()),
+            });
+        res.map_err(|e| e.with_path(state.path()))
+    }
+
+    ///
+    /// The state to start a render with, as configured.
+    ///
+    pub(crate) fn state(&self) -> State {
+        let mut state = State::new(self.mode);
+        state.auto_flush = self.auto_flush;
+        state.invalid_chars = self.invalid_chars;
+        state.escaping = match (self.minimal, self.mode) {
+            (false, _) => tools::Escaping::Full,
+            (true, Mode::Xml) => tools::Escaping::MinimalXml,
+            (true, Mode::Html) => tools::Escaping::MinimalHtml,
+        };
+        state.ascii = self.ascii;
+        state.duplicates = self.duplicates;
+        state
+    }
+
+    ///
+    /// The layout that holds back output, if a line width is set.
+    ///
+    pub(crate) fn layout(&self) -> Option<RefCell<Layout>> {
+        self.width.map(|width| RefCell::new(Layout::new(width)))
+    }
+
+    ///
+    /// Call `func` with a writer to `sink` that uses this renderer's formatter.
+    ///
+    pub(crate) fn with_writer<R>(
+        &mut self,
+        state: &mut State,
+        layout: Option<&RefCell<Layout>>,
+        sink: &mut dyn tools::Sink,
+        func: impl FnOnce(ElemWrite) -> Result<R, Error>,
+    ) -> Result<R, Error> {
+        match layout {
+            Some(layout) => crate::layout::with(layout, &mut self.fmt, state, sink, func),
+            None => func(ElemWrite(SinkWrap(sink), &mut self.fmt, state)),
+        }
+    }
+
+    ///
+    /// Render to a `std::io::Write`. Output is buffered, and a failure of the
+    /// underlying writer is returned as is.
+    ///
+    pub fn render_io<E: Elem + Locked, W: std::io::Write>(
+        &mut self,
+        elem: E,
+        writer: W,
+    ) -> std::io::Result<()> {
+        let mut w = tools::upgrade_write(std::io::BufWriter::new(writer));
+        let res = self.render_sink(elem, &mut w);
+        finish_io(res, w)
+    }
+
+    ///
+    /// Render to a `std::io::Write`, allowing for escaping elements.
+    ///
+    pub fn render_io_escapable<E: Elem, W: std::io::Write>(
+        &mut self,
+        elem: E,
+        writer: W,
+    ) -> std::io::Result<()> {
+        let mut w = tools::upgrade_write(std::io::BufWriter::new(writer));
+        let res = self.render_sink_escapable(elem, &mut w);
+        finish_io(res, w)
+    }
+
+    ///
+    /// Render to a new string.
+    ///
+    pub fn render_to_string<E: Elem + Locked>(&mut self, elem: E) -> Result<String, Error> {
+        let mut s = String::with_capacity(STRING_CAPACITY);
+        self.render(elem, &mut s)?;
+        Ok(s)
     }
 }
 
-// pub trait Fmt {
-//     fn push(&mut self);
-//     fn pop(&mut self);
-//     fn tabs(&mut self, w: &mut dyn fmt::Write) -> fmt::Result;
-//     fn end_tag(&mut self, w: &mut dyn fmt::Write) -> fmt::Result;
-//     fn set_inline_mode(&mut self, val: bool);
-//     fn is_inline_mode(&mut self) -> bool;
-// }
+///
+/// Initial capacity of strings rendered to, enough for a small document
+/// without reallocating.
+///
+const STRING_CAPACITY: usize = 1024;
 
+fn finish_io<W: std::io::Write>(
+    res: Result<(), Error>,
+    mut w: tools::Adaptor<std::io::BufWriter<W>>,
+) -> std::io::Result<()> {
+    match res {
+        Ok(()) => std::io::Write::flush(&mut w.inner),
+        Err(e) => {
+            w.error?;
+            Err(e.into())
+        }
+    }
+}
+
+///
+/// How elements are serialized.
+///
+/// The same element tree can be written out as xml or as html5.
+///
+/// ```
+/// use tagu::build;
+/// use tagu::prelude::*;
+/// use tagu::render::Mode;
+/// let k = build::elem("p").append(build::single("br")).append(build::single("span"));
+/// let k = build::doctype("html").chain(k);
+///
+/// let mut s = String::new();
+/// tagu::render_compact(k, &mut s).unwrap();
+/// assert_eq!(s, "<!DOCTYPE html><p><br/><span/></p>");
+///
+/// # let k = build::elem("p").append(build::single("br")).append(build::single("span"));
+/// # let k = build::doctype("html").chain(k);
+/// let mut s = String::new();
+/// tagu::render::Renderer::compact().with_mode(Mode::Html).render(k, &mut s).unwrap();
+/// assert_eq!(s, "<!DOCTYPE html><p><br><span></span></p>");
+/// ```
+///
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Mode {
+    /// Every element without children is self closing. The content of `script` and `style`
+    /// is only escaped as much as xml requires.
+    #[default]
+    Xml,
+    /// Void elements like `<br>` have no end tag, and other elements are never self closing.
+    /// Boolean attributes whose value is empty or their own name are written bare.
+    /// The content of `script` and `style` is written unescaped, see `tools::Context`.
+    /// Tags also have to start with an ascii letter. Attribute names are checked as in xml,
+    /// which is stricter than html already.
+    Html,
+}
+
+///
+/// State that lives for the duration of one render.
+///
+pub(crate) struct State {
+    pub(crate) mode: Mode,
+    /// Set while inside a raw text element like `<script>`.
+    pub(crate) raw_text: Option<&'static str>,
+    /// Flush after every element that is at most one level deep.
+    pub(crate) auto_flush: bool,
+    /// The namespaces in scope.
+    pub(crate) ns: ns::Scopes,
+    pub(crate) invalid_chars: tools::InvalidChars,
+    pub(crate) escaping: tools::Escaping,
+    /// Only write ascii.
+    pub(crate) ascii: bool,
+    /// Set while inside an element whose content is written exactly as given.
+    pub(crate) preserve: bool,
+    /// Set while inside a void element like `<br>`, which can't have content.
+    pub(crate) void: bool,
+    /// Set while inside an html element like `<script>` or `<textarea>`, which only holds text.
+    pub(crate) text_only: bool,
+    /// Carried from one text node to the next, so that a sequence like `</script`
+    /// can't be split across them. Markup in between starts it over.
+    pub(crate) scan: tools::Scan,
+    pub(crate) duplicates: attr::Duplicates,
+    /// Reused for the attributes of every start tag.
+    pub(crate) attrs: attr::AttrBuf,
+    /// The tags of the open elements, back to back.
+    names: String,
+    /// Where each tag in `names` starts.
+    open: Vec<usize>,
+}
+
+impl State {
+    pub(crate) fn new(mode: Mode) -> Self {
+        State {
+            mode,
+            raw_text: None,
+            auto_flush: false,
+            ns: ns::Scopes::new(),
+            invalid_chars: tools::InvalidChars::Replace,
+            escaping: tools::Escaping::Full,
+            ascii: false,
+            preserve: false,
+            void: false,
+            text_only: false,
+            scan: tools::Scan::default(),
+            duplicates: attr::Duplicates::Allow,
+            attrs: attr::AttrBuf::default(),
+            names: String::new(),
+            open: Vec::new(),
+        }
+    }
+
+    ///
+    /// Push a tag, qualified with a namespace prefix if it has one.
+    ///
+    pub(crate) fn push_name<D: tools::AsName + ?Sized>(
+        &mut self,
+        prefix: Option<&str>,
+        tag: &D,
+    ) -> Result<(), Error> {
+        use fmt::Write;
+        let start = self.names.len();
+        if let Some(p) = prefix {
+            write!(self.names, "{}:", p)?;
+        }
+        let html = self.mode == Mode::Html;
+        let valid = match tag.checked() {
+            // Only what depends on the mode and the prefix is left to check.
+            Some(tag) => {
+                self.names.push_str(tag.as_str());
+                let name = &self.names[start..];
+                prefix.is_none_or(tools::is_name)
+                    && (!html || name.starts_with(|c: char| c.is_ascii_alphabetic()))
+                    && (!self.ascii || (tag.is_ascii() && prefix.is_none_or(str::is_ascii)))
+            }
+            None => {
+                tag.write_name(&mut self.names)?;
+                let name = &self.names[start..];
+                tools::is_tag_name(name, html) && (!self.ascii || name.is_ascii())
+            }
+        };
+        if !valid {
+            let name = self.names.split_off(start);
+            return Err(Error::new(ErrorKind::Name(name)));
+        }
+        self.open.push(start);
+        Ok(())
+    }
+
+    pub(crate) fn pop_name(&mut self) {
+        if let Some(start) = self.open.pop() {
+            self.names.truncate(start);
+        }
+    }
+
+    ///
+    /// The number of open elements.
+    ///
+    pub(crate) fn depth(&self) -> usize {
+        self.open.len()
+    }
+
+    pub(crate) fn top_name(&self) -> &str {
+        let start = self.open.last().copied().unwrap_or(0);
+        &self.names[start..]
+    }
+
+    ///
+    /// The tags of the open elements, outermost first.
+    ///
+    pub(crate) fn path(&self) -> Vec<String> {
+        let ends = self.open.iter().skip(1).copied().chain([self.names.len()]);
+        self.open
+            .iter()
+            .zip(ends)
+            .map(|(&a, b)| self.names[a..b].to_string())
+            .collect()
+    }
+
+    ///
+    /// Escape for the given context, as configured.
+    ///
+    pub(crate) fn escaper<T: fmt::Write>(
+        &self,
+        writer: T,
+        context: tools::Context,
+    ) -> tools::Escaper<T> {
+        tools::escaper(writer, context)
+            .with_invalid_chars(self.invalid_chars)
+            .with_escaping(self.escaping)
+            .with_ascii(self.ascii)
+    }
+
+    ///
+    /// Escape element content, carrying on from the content before it.
+    ///
+    pub(crate) fn text_escaper<T: fmt::Write>(
+        &mut self,
+        writer: T,
+    ) -> tools::Escaper<T, &mut tools::Scan> {
+        let context = self.text_context();
+        self.escaper(writer, context).with_scan(&mut self.scan)
+    }
+
+    ///
+    /// The escaping context of element content at the current position.
+    ///
+    pub(crate) fn text_context(&self) -> tools::Context {
+        match (self.raw_text, self.mode) {
+            (None, _) => tools::Context::Text,
+            (Some(_), Mode::Xml) => tools::Context::XmlRawText,
+            (Some(tag), Mode::Html) => tools::Context::HtmlRawText(tag),
+        }
+    }
+}
+
+///
+/// The formatting policy used while rendering.
+///
+/// Elements call into this at well defined points, namely before a tag is written (`tabs()`),
+/// after a tag is written (`end_tag()`) and before every attribute (`attr_sep()`).
+///
+pub trait Fmt {
+    /// Called when an element is opened.
+    fn push(&mut self);
+    /// Called when an element is closed.
+    fn pop(&mut self);
+    /// Called before an element is written.
+    fn tabs(&mut self, w: &mut dyn fmt::Write) -> fmt::Result;
+    /// Called after an element is written.
+    fn end_tag(&mut self, w: &mut dyn fmt::Write) -> fmt::Result;
+    fn set_inline_mode(&mut self, val: bool);
+    fn is_inline_mode(&mut self) -> bool;
+    /// Replace the indentation string, returning the previous one.
+    fn swap_tab(&mut self, tab: &'static str) -> &'static str;
+
+    /// Called before every attribute is written.
+    fn attr_sep(&mut self, w: &mut dyn fmt::Write) -> fmt::Result {
+        w.write_char(' ')
+    }
+
+    /// Called instead of `end_tag()` after a line of multi-line content,
+    /// that must not be joined with the next line.
+    fn line_break(&mut self, w: &mut dyn fmt::Write) -> fmt::Result {
+        self.end_tag(w)
+    }
+}
+
+///
+/// The default formatter. Indents each nested element and puts every element on its own line.
+///
 pub struct PrettyFmt {
     tabs: usize,
     pub tab_char: &'static str,
+    newline: &'static str,
     inline: bool,
 }
 
@@ -69,57 +702,93 @@ impl PrettyFmt {
         PrettyFmt {
             tabs: 0,
             tab_char: "\t",
+            newline: "\n",
             inline: false,
         }
     }
+
+    ///
+    /// Set the string used for one level of indentation.
+    ///
+    pub fn with_tab(mut self, tab: &'static str) -> Self {
+        self.tab_char = tab;
+        self
+    }
+
+    ///
+    /// Set the string written after every element, `"\r\n"` for example.
+    ///
+    pub fn with_newline(mut self, newline: &'static str) -> Self {
+        self.newline = newline;
+        self
+    }
 }
-impl PrettyFmt {
-    pub fn set_inline_mode(&mut self, val: bool) {
+
+impl Fmt for PrettyFmt {
+    fn set_inline_mode(&mut self, val: bool) {
         self.inline = val;
     }
-    pub fn is_inline_mode(&mut self) -> bool {
+    fn is_inline_mode(&mut self) -> bool {
         self.inline
     }
-    pub fn tabs(&mut self, w: &mut dyn fmt::Write) -> fmt::Result {
+    fn tabs(&mut self, w: &mut dyn fmt::Write) -> fmt::Result {
         if !self.inline {
             for _ in 0..self.tabs {
-                write!(w, "{}", self.tab_char)?;
+                w.write_str(self.tab_char)?;
             }
         }
 
         Ok(())
     }
-    pub fn push(&mut self) {
-        //if !self.inline {
+    fn push(&mut self) {
         self.tabs += 1;
-        //}
     }
-    pub fn pop(&mut self) {
-        //if !self.inline {
+    fn pop(&mut self) {
         self.tabs -= 1;
-        //}
     }
-    pub fn end_tag(&mut self, w: &mut dyn fmt::Write) -> fmt::Result {
+    fn end_tag(&mut self, w: &mut dyn fmt::Write) -> fmt::Result {
         if !self.inline {
-            writeln!(w)?;
+            w.write_str(self.newline)?;
         }
         Ok(())
     }
+    fn swap_tab(&mut self, tab: &'static str) -> &'static str {
+        std::mem::replace(&mut self.tab_char, tab)
+    }
 }
 
-// pub struct NoFmt;
-// impl Fmt for NoFmt {
-//     fn tabs(&mut self, _: &mut dyn fmt::Write) -> fmt::Result {
-//         Ok(())
-//     }
-//     fn push(&mut self) {}
-//     fn pop(&mut self) {}
-//     fn end_tag(&mut self, _: &mut dyn fmt::Write) -> fmt::Result {
-//         Ok(())
-//     }
-//     fn set_inline_mode(&mut self, _: bool) {}
-
-//     fn is_inline_mode(&mut self) -> bool {
-//         true
-//     }
-// }
+///
+/// A formatter that writes no formatting whitespace at all.
+///
+/// Whitespace that is part of the content, such as text or raw elements, is left alone.
+///
+/// ```
+/// use tagu::build;
+/// use tagu::prelude::*;
+/// let mut s = String::new();
+/// let k = build::elem("a").append(build::elem("b").append(build::raw("hello world")));
+/// tagu::render_compact(k, &mut s).unwrap();
+/// assert_eq!(s, "<a><b>hello world</b></a>");
+/// ```
+///
+#[derive(Copy, Clone, Debug, Default)]
+pub struct NoFmt;
+
+impl Fmt for NoFmt {
+    fn tabs(&mut self, _: &mut dyn fmt::Write) -> fmt::Result {
+        Ok(())
+    }
+    fn push(&mut self) {}
+    fn pop(&mut self) {}
+    fn end_tag(&mut self, _: &mut dyn fmt::Write) -> fmt::Result {
+        Ok(())
+    }
+    fn set_inline_mode(&mut self, _: bool) {}
+
+    fn is_inline_mode(&mut self) -> bool {
+        true
+    }
+    fn swap_tab(&mut self, tab: &'static str) -> &'static str {
+        tab
+    }
+}
@@ -24,24 +24,32 @@ pub struct Popper<E, O> {
 pub struct ElemStack<'a, T>(ElemStackEscapable<'a, T>);
 
 impl<'a, T> ElemStack<'a, T> {
-    pub fn put<E: Elem + Locked>(&mut self, elem: E) -> fmt::Result {
+    pub fn put<E: Elem + Locked>(&mut self, elem: E) -> Result<(), Error> {
         self.0.put(elem)
     }
     pub fn push<E: Elem + Locked>(
         self,
         elem: E,
-    ) -> Result<ElemStack<'a, Popper<E::Tail, T>>, fmt::Error> {
-        self.0.push(elem).map(|a| ElemStack(a))
+    ) -> Result<ElemStack<'a, Popper<E::Tail, T>>, Error> {
+        self.0.push(elem).map(ElemStack)
     }
 
-    pub fn writer(&mut self) -> tools::EscapeGuard<WriteWrap> {
+    pub fn writer(&mut self) -> tools::Escaper<WriteWrap<'_>, &mut tools::Scan> {
         self.0.writer.writer()
     }
+
+    ///
+    /// Push everything written so far to the destination, if it supports that.
+    /// See `tools::Sink::flush()`.
+    ///
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.0.flush()
+    }
 }
 
 impl<'a, P: Pop> ElemStack<'a, P> {
-    pub fn pop(self) -> Result<ElemStack<'a, P::Last>, fmt::Error> {
-        self.0.pop().map(|a| ElemStack(a))
+    pub fn pop(self) -> Result<ElemStack<'a, P::Last>, Error> {
+        self.0.pop().map(ElemStack)
     }
 }
 
@@ -51,13 +59,14 @@ pub struct ElemStackEscapable<'a, T> {
 }
 
 impl<'a, T> ElemStackEscapable<'a, T> {
-    pub fn put<E: Elem>(&mut self, elem: E) -> fmt::Result {
+    pub fn put<E: Elem>(&mut self, elem: E) -> Result<(), Error> {
         self.writer.render_inner(elem)
     }
     pub fn push<E: Elem>(
         mut self,
         elem: E,
-    ) -> Result<ElemStackEscapable<'a, Popper<E::Tail, T>>, fmt::Error> {
+    ) -> Result<ElemStackEscapable<'a, Popper<E::Tail, T>>, Error> {
+        self.writer.check_content()?;
         let tail = elem.render_head(self.writer.borrow_mut2())?;
         Ok(self.push_tail(tail))
     }
@@ -71,15 +80,24 @@ impl<'a, T> ElemStackEscapable<'a, T> {
         }
     }
 
-    pub fn writer_escapable(&mut self) -> WriteWrap {
+    pub fn writer_escapable(&mut self) -> WriteWrap<'_> {
         self.writer.writer_escapable()
     }
+
+    ///
+    /// Push everything written so far to the destination, if it supports that.
+    /// See `tools::Sink::flush()`.
+    ///
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.writer.flush()
+    }
 }
 
 impl<'a, P: Pop> ElemStackEscapable<'a, P> {
-    pub fn pop(mut self) -> Result<ElemStackEscapable<'a, P::Last>, fmt::Error> {
+    pub fn pop(mut self) -> Result<ElemStackEscapable<'a, P::Last>, Error> {
         let (e, l) = self.inner.next();
         e.render(self.writer.borrow_mut2())?;
+        self.writer.boundary()?;
 
         Ok(ElemStackEscapable {
             writer: self.writer,
@@ -93,7 +111,7 @@ pub struct Sess<F> {
 }
 impl<F> Sess<F>
 where
-    F: FnOnce(ElemStack<Sentinel>) -> Result<ElemStack<Sentinel>, fmt::Error>,
+    F: FnOnce(ElemStack<Sentinel>) -> Result<ElemStack<Sentinel>, Error>,
 {
     pub fn new(func: F) -> Self {
         Self { func }
@@ -104,10 +122,10 @@ impl<F> Locked for Sess<F> {}
 
 impl<F> Elem for Sess<F>
 where
-    F: FnOnce(ElemStack<Sentinel>) -> Result<ElemStack<Sentinel>, fmt::Error>,
+    F: FnOnce(ElemStack<Sentinel>) -> Result<ElemStack<Sentinel>, Error>,
 {
     type Tail = ();
-    fn render_head(self, writer: ElemWrite) -> Result<Self::Tail, fmt::Error> {
+    fn render_head(self, writer: ElemWrite) -> Result<Self::Tail, Error> {
         let k = ElemStack(ElemStackEscapable {
             writer,
             inner: Sentinel { _p: () },
@@ -122,7 +140,7 @@ pub struct SessEscapable<F> {
 }
 impl<F> SessEscapable<F>
 where
-    F: FnOnce(ElemStackEscapable<Sentinel>) -> Result<ElemStackEscapable<Sentinel>, fmt::Error>,
+    F: FnOnce(ElemStackEscapable<Sentinel>) -> Result<ElemStackEscapable<Sentinel>, Error>,
 {
     pub fn new(func: F) -> Self {
         Self { func }
@@ -131,10 +149,10 @@ where
 
 impl<F> Elem for SessEscapable<F>
 where
-    F: FnOnce(ElemStackEscapable<Sentinel>) -> Result<ElemStackEscapable<Sentinel>, fmt::Error>,
+    F: FnOnce(ElemStackEscapable<Sentinel>) -> Result<ElemStackEscapable<Sentinel>, Error>,
 {
     type Tail = ();
-    fn render_head(self, writer: ElemWrite) -> Result<Self::Tail, fmt::Error> {
+    fn render_head(self, writer: ElemWrite) -> Result<Self::Tail, Error> {
         let k = ElemStackEscapable {
             writer,
             inner: Sentinel { _p: () },
@@ -148,13 +166,13 @@ where
 /// If you dont want to use a closure, you can implement this trait
 ///
 pub trait ElemOuter {
-    fn render<'a>(self, w: ElemStack<'a, Sentinel>) -> Result<ElemStack<'a, Sentinel>, fmt::Error>;
+    fn render<'a>(self, w: ElemStack<'a, Sentinel>) -> Result<ElemStack<'a, Sentinel>, Error>;
 }
 impl<E: ElemOuter> Locked for E {}
 impl<E: ElemOuter> Elem for E {
     type Tail = ();
 
-    fn render_head(self, writer: ElemWrite) -> Result<Self::Tail, fmt::Error> {
+    fn render_head(self, writer: ElemWrite) -> Result<Self::Tail, Error> {
         let k = ElemStack(ElemStackEscapable {
             writer,
             inner: Sentinel { _p: () },
@@ -0,0 +1,356 @@
+//!
+//! Rendering a chunk at a time, as the output is pulled.
+//!
+
+use super::*;
+use layout::Layout;
+use render::{Fmt, Renderer, State};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+
+///
+/// A piece of output, and whether the consumer should flush it right away.
+///
+struct Chunk {
+    data: String,
+    #[cfg_attr(not(any(feature = "async", feature = "tokio")), allow(dead_code))]
+    flush: bool,
+}
+
+///
+/// Collects output into chunks that are ready to be pulled.
+///
+struct ChunkBuffer {
+    buffer: String,
+    chunk_size: usize,
+    /// Split chunks at exactly `chunk_size`, instead of at the first element boundary past it.
+    strict: bool,
+    ready: VecDeque<Chunk>,
+}
+
+impl ChunkBuffer {
+    fn new(chunk_size: usize, strict: bool) -> Self {
+        let chunk_size = chunk_size.max(1);
+        ChunkBuffer {
+            buffer: String::with_capacity(chunk_size),
+            chunk_size,
+            strict,
+            ready: VecDeque::new(),
+        }
+    }
+
+    fn send(&mut self, flush: bool) {
+        if !self.buffer.is_empty() {
+            let data = std::mem::replace(&mut self.buffer, String::with_capacity(self.chunk_size));
+            self.ready.push_back(Chunk { data, flush });
+        }
+    }
+}
+
+impl fmt::Write for ChunkBuffer {
+    fn write_str(&mut self, mut s: &str) -> fmt::Result {
+        if !self.strict {
+            self.buffer.push_str(s);
+            return Ok(());
+        }
+
+        while !s.is_empty() {
+            let room = self.chunk_size - self.buffer.len();
+            let mut end = room.min(s.len());
+            while !s.is_char_boundary(end) {
+                end -= 1;
+            }
+            if end == 0 && self.buffer.is_empty() {
+                // A single character that is bigger than a chunk.
+                end = s.chars().next().map(char::len_utf8).unwrap_or(s.len());
+            }
+            self.buffer.push_str(&s[..end]);
+            s = &s[end..];
+            if !s.is_empty() || self.buffer.len() >= self.chunk_size {
+                self.send(false);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl tools::Sink for ChunkBuffer {
+    fn boundary(&mut self) -> fmt::Result {
+        if !self.strict && self.buffer.len() >= self.chunk_size {
+            self.send(true);
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> fmt::Result {
+        self.send(true);
+        Ok(())
+    }
+}
+
+///
+/// How far the render got.
+///
+enum Stage<E: Elem, I> {
+    Head(E, I),
+    Children(E::Tail, I),
+    Done,
+}
+
+///
+/// An iterator of rendered chunks. Created by `Renderer::render_chunks()`.
+///
+/// Nothing is rendered until a chunk is pulled, and then only as much as it takes
+/// to fill the chunk, one child at a time.
+///
+pub struct Chunks<F, E: Elem, I> {
+    renderer: Renderer<F>,
+    state: State,
+    layout: Option<RefCell<Layout>>,
+    out: ChunkBuffer,
+    stage: Stage<E, I>,
+    /// Returned once the chunks written before it have been pulled.
+    error: Option<Error>,
+}
+
+impl<F: Fmt, E: Elem, I: Iterator> Chunks<F, E, I>
+where
+    I::Item: Elem,
+{
+    ///
+    /// Render the next step, which is the start tag, one child, or the end tag.
+    ///
+    fn step(&mut self) -> Result<(), Error> {
+        let Chunks {
+            renderer,
+            state,
+            layout,
+            out,
+            stage,
+            ..
+        } = self;
+        let layout = layout.as_ref();
+        match std::mem::replace(stage, Stage::Done) {
+            Stage::Head(elem, children) => {
+                let tail = renderer.with_writer(state, layout, out, |w| elem.render_head(w))?;
+                *stage = Stage::Children(tail, children);
+            }
+            Stage::Children(tail, mut children) => match children.next() {
+                Some(child) => {
+                    renderer.with_writer(state, layout, out, |mut w| w.render_inner(child))?;
+                    *stage = Stage::Children(tail, children);
+                }
+                None => {
+                    renderer.with_writer(state, layout, out, |mut w| {
+                        tail.render(w.borrow_mut2())?;
+                        w.boundary()
+                    })?;
+                    if let Some(layout) = layout {
+                        layout::finish(layout, out)?;
+                    }
+                    out.send(true);
+                }
+            },
+            Stage::Done => {}
+        }
+        Ok(())
+    }
+
+    fn next_chunk(&mut self) -> Option<Result<Chunk, Error>> {
+        loop {
+            if let Some(a) = self.out.ready.pop_front() {
+                return Some(Ok(a));
+            }
+            if let Some(e) = self.error.take() {
+                return Some(Err(e));
+            }
+            if let Stage::Done = self.stage {
+                return None;
+            }
+            if let Err(e) = self.step() {
+                self.stage = Stage::Done;
+                self.error = Some(e.with_path(self.state.path()));
+            }
+        }
+    }
+}
+
+impl<F: Fmt, E: Elem, I: Iterator> Iterator for Chunks<F, E, I>
+where
+    I::Item: Elem,
+{
+    type Item = Result<String, Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_chunk().map(|a| a.map(|a| a.data))
+    }
+}
+
+impl<F: Fmt> Renderer<F> {
+    ///
+    /// Render `elem` with `children` appended to it, into chunks of at most `chunk_size` bytes
+    /// that are pulled from an iterator.
+    ///
+    /// Rendering happens on the calling thread as the chunks are pulled, one child at a time.
+    /// So only the output of one child is held in memory, and children are only taken from
+    /// `children` once the chunks before them have been pulled. Dropping the iterator stops the render.
+    ///
+    /// ```
+    /// use tagu::build;
+    /// use tagu::prelude::*;
+    /// use tagu::render::Renderer;
+    /// let rows = (0..3).map(|i| build::elem("row").inline().append(build::raw(i)));
+    /// let mut chunks = Renderer::new().render_chunks(build::elem("table"), rows, 16);
+    /// assert_eq!(chunks.next().unwrap().unwrap(), "<table>\n\t<row>0<");
+    /// assert_eq!(chunks.map(|a| a.unwrap()).collect::<String>(), "/row>\n\t<row>1</row>\n\t<row>2</row>\n</table>\n");
+    /// ```
+    ///
+    pub fn render_chunks<E: Elem + Locked, I: IntoIterator>(
+        self,
+        elem: E,
+        children: I,
+        chunk_size: usize,
+    ) -> Chunks<F, E, I::IntoIter>
+    where
+        I::Item: Elem + Locked,
+    {
+        self.chunks(elem, children, chunk_size, true)
+    }
+
+    fn chunks<E: Elem, I: IntoIterator>(
+        self,
+        elem: E,
+        children: I,
+        chunk_size: usize,
+        strict: bool,
+    ) -> Chunks<F, E, I::IntoIter> {
+        Chunks {
+            state: self.state(),
+            layout: self.layout(),
+            renderer: self,
+            out: ChunkBuffer::new(chunk_size, strict),
+            stage: Stage::Head(elem, children.into_iter()),
+            error: None,
+        }
+    }
+}
+
+#[cfg(any(feature = "async", feature = "tokio"))]
+mod asynch {
+    use super::*;
+    use std::future::poll_fn;
+    use std::io;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    ///
+    /// Chunk size used when rendering to an async writer.
+    /// The writer is flushed at the first element boundary past each chunk.
+    ///
+    const CHUNK_SIZE: usize = 8 * 1024;
+
+    ///
+    /// The parts of an async writer that are needed, so that futures and tokio writers
+    /// can be driven by the same code.
+    ///
+    trait AsyncSink {
+        fn poll_write(&mut self, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>>;
+        fn poll_flush(&mut self, cx: &mut Context) -> Poll<io::Result<()>>;
+    }
+
+    #[cfg(feature = "async")]
+    struct Futures<W>(W);
+
+    #[cfg(feature = "async")]
+    impl<W: futures_io::AsyncWrite + Unpin> AsyncSink for Futures<W> {
+        fn poll_write(&mut self, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+            Pin::new(&mut self.0).poll_write(cx, buf)
+        }
+        fn poll_flush(&mut self, cx: &mut Context) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.0).poll_flush(cx)
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    struct Tokio<W>(W);
+
+    #[cfg(feature = "tokio")]
+    impl<W: tokio::io::AsyncWrite + Unpin> AsyncSink for Tokio<W> {
+        fn poll_write(&mut self, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+            Pin::new(&mut self.0).poll_write(cx, buf)
+        }
+        fn poll_flush(&mut self, cx: &mut Context) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.0).poll_flush(cx)
+        }
+    }
+
+    ///
+    /// Pull chunks and write each one out before rendering the next.
+    ///
+    async fn drive<F: Fmt, E: Elem, I: Iterator>(
+        mut chunks: Chunks<F, E, I>,
+        mut w: impl AsyncSink,
+    ) -> io::Result<()>
+    where
+        I::Item: Elem,
+    {
+        while let Some(chunk) = chunks.next_chunk() {
+            let chunk = chunk?;
+            let mut buf = chunk.data.as_bytes();
+            while !buf.is_empty() {
+                let n = poll_fn(|cx| w.poll_write(cx, buf)).await?;
+                if n == 0 {
+                    return Err(io::ErrorKind::WriteZero.into());
+                }
+                buf = &buf[n..];
+            }
+            if chunk.flush {
+                poll_fn(|cx| w.poll_flush(cx)).await?;
+            }
+        }
+        Ok(())
+    }
+
+    impl<F: Fmt> Renderer<F> {
+        ///
+        /// Render `elem` with `children` appended to it to a `futures_io::AsyncWrite`.
+        ///
+        /// Like `render_chunks()`, children are rendered one at a time on the polling task,
+        /// and each one is written out before the next is rendered. The writer is flushed
+        /// at element boundaries, roughly every 8 KiB, and at the end.
+        ///
+        #[cfg(feature = "async")]
+        pub async fn render_async<E: Elem + Locked, I: IntoIterator>(
+            self,
+            elem: E,
+            children: I,
+            writer: impl futures_io::AsyncWrite + Unpin,
+        ) -> io::Result<()>
+        where
+            I::Item: Elem + Locked,
+        {
+            let chunks = self.chunks(elem, children, CHUNK_SIZE, false);
+            drive(chunks, Futures(writer)).await
+        }
+
+        ///
+        /// Render `elem` with `children` appended to it to a `tokio::io::AsyncWrite`.
+        ///
+        /// Like `render_chunks()`, children are rendered one at a time on the polling task,
+        /// and each one is written out before the next is rendered. The writer is flushed
+        /// at element boundaries, roughly every 8 KiB, and at the end.
+        ///
+        #[cfg(feature = "tokio")]
+        pub async fn render_tokio<E: Elem + Locked, I: IntoIterator>(
+            self,
+            elem: E,
+            children: I,
+            writer: impl tokio::io::AsyncWrite + Unpin,
+        ) -> io::Result<()>
+        where
+            I::Item: Elem + Locked,
+        {
+            let chunks = self.chunks(elem, children, CHUNK_SIZE, false);
+            drive(chunks, Tokio(writer)).await
+        }
+    }
+}
@@ -2,6 +2,7 @@
 //! Misc functions
 //!
 
+use std::borrow::BorrowMut;
 use std::fmt;
 
 ///
@@ -18,11 +19,23 @@ pub fn escape_guard<T: std::fmt::Write>(a: T) -> EscapeGuard<T> {
 ///
 pub struct EscapeGuard<T> {
     writer: T,
+    ascii: bool,
 }
 
 impl<T: std::fmt::Write> EscapeGuard<T> {
     pub fn new(writer: T) -> EscapeGuard<T> {
-        EscapeGuard { writer }
+        EscapeGuard {
+            writer,
+            ascii: false,
+        }
+    }
+
+    ///
+    /// Also write every non-ascii character as a numeric character reference.
+    ///
+    pub fn with_ascii(mut self, ascii: bool) -> Self {
+        self.ascii = ascii;
+        self
     }
 }
 
@@ -40,6 +53,8 @@ impl<T: std::fmt::Write> std::fmt::Write for EscapeGuard<T> {
 
             if let Some(r) = r {
                 self.writer.write_str(r)?;
+            } else if self.ascii && !c.is_ascii() {
+                write!(self.writer, "&#x{:X};", c as u32)?;
             } else {
                 self.writer.write_char(c)?;
             }
@@ -48,6 +63,798 @@ impl<T: std::fmt::Write> std::fmt::Write for EscapeGuard<T> {
     }
 }
 
+///
+/// Where a piece of text ends up in the document. Decides how it is escaped.
+///
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Context {
+    /// Element content. `&` `<` `>` are escaped.
+    Text,
+    /// A double quoted attribute value. `&` `<` `>` `"` are escaped, as are
+    /// newlines, carriage returns and tabs, so that a parser does not normalize them to spaces.
+    Attr,
+    /// The content of a `<script>` or `<style>` in xml. Only `&` `<` and the `>` of `]]>` are escaped,
+    /// so that css like `a > b` reads naturally.
+    XmlRawText,
+    /// The content of a `<script>` or `<style>` in html. Nothing is escaped since the html parser
+    /// does not decode it, but writing the closing tag of the element (`</script` for example) is an error.
+    HtmlRawText(&'static str),
+    /// The content of a comment. Nothing is escaped, but `--` is an error, as is
+    /// starting with `>` or `->` and ending with `-`.
+    Comment,
+    /// The content of a CDATA section. Nothing is escaped, and `]]>` is split across two sections.
+    Cdata,
+    /// The data of a processing instruction. Nothing is escaped, but `?>` is an error.
+    ProcessingInstruction,
+}
+
+///
+/// What to do with characters that xml 1.0 does not allow anywhere in a document,
+/// like `\u{0}` or `\u{1B}`. Only tab, newline and carriage return are allowed below `\u{20}`.
+/// (Lone surrogates cannot occur in a rust string, so they never reach the output.)
+///
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum InvalidChars {
+    /// Write `\u{FFFD}` instead.
+    #[default]
+    Replace,
+    /// Leave them out.
+    Strip,
+    /// Fail with `ErrorKind::InvalidChar`.
+    Error,
+}
+
+impl InvalidChars {
+    ///
+    /// Whether `c` is left out of the output.
+    ///
+    fn strips(self, c: char) -> bool {
+        self == InvalidChars::Strip && !is_xml_char(c)
+    }
+}
+
+///
+/// Which characters are escaped in text and attribute values.
+///
+/// ```
+/// use std::fmt::Write;
+/// use tagu::tools::{escaper, Context, Escaping};
+/// let mut s = String::new();
+/// let mut w = escaper(&mut s, Context::Text).with_escaping(Escaping::MinimalXml);
+/// write!(w, "a > b && c ]]> d").unwrap();
+/// let mut w = escaper(&mut s, Context::Attr).with_escaping(Escaping::MinimalHtml);
+/// write!(w, " <'\"> ").unwrap();
+/// let mut w = escaper(&mut s, Context::Text).with_ascii(true);
+/// write!(w, "né ✓").unwrap();
+/// assert_eq!(s, "a > b &amp;&amp; c ]]&gt; d <'&quot;> n&#xE9; &#x2713;");
+/// ```
+///
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Escaping {
+    /// `&` `<` `>` everywhere, and `"` in attribute values.
+    #[default]
+    Full,
+    /// Only what xml requires. `&` `<` and the `>` of `]]>` in text,
+    /// `&` `<` `"` in attribute values.
+    MinimalXml,
+    /// Only what html requires. `&` `<` in text, `&` `"` in attribute values.
+    MinimalHtml,
+}
+
+///
+/// Whether xml 1.0 allows `c` in a document.
+///
+fn is_xml_char(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\r' | '\u{20}'..='\u{D7FF}' | '\u{E000}'..='\u{FFFD}' | '\u{10000}'..)
+}
+
+///
+/// Writer adaptor that escapes according to a `Context`.
+///
+/// ```
+/// use std::fmt::Write;
+/// use tagu::tools::{escaper, Context};
+/// let mut s = String::new();
+/// write!(escaper(&mut s, Context::Text), "a > b").unwrap();
+/// write!(escaper(&mut s, Context::XmlRawText), " a > b").unwrap();
+/// assert_eq!(s, "a &gt; b a > b");
+///
+/// let mut w = escaper(String::new(), Context::HtmlRawText("script"));
+/// assert!(write!(w, "if (a < b && c > d) {{}}").is_ok());
+/// assert!(write!(w, "</scr").and_then(|_| write!(w, "IPT>")).is_err());
+///
+/// let mut w = escaper(String::new(), Context::Comment);
+/// assert!(write!(w, "a - b").is_ok());
+/// assert!(write!(w, " -").and_then(|_| write!(w, "- c")).is_err());
+///
+/// let mut s = String::new();
+/// write!(escaper(&mut s, Context::Attr), "a\tb\nc\u{1B}").unwrap();
+/// assert_eq!(s, "a&#9;b&#10;c\u{FFFD}");
+///
+/// let mut s = String::new();
+/// write!(escaper(&mut s, Context::Cdata), "a]]>b").unwrap();
+/// assert_eq!(s, "a]]]]><![CDATA[>b");
+///
+/// let mut w = escaper(String::new(), Context::ProcessingInstruction);
+/// assert!(write!(w, "a > b").is_ok());
+/// assert!(write!(w, " ?").and_then(|_| write!(w, ">")).is_err());
+/// ```
+///
+pub struct Escaper<T, S = Scan> {
+    writer: T,
+    context: Context,
+    invalid_chars: InvalidChars,
+    escaping: Escaping,
+    ascii: bool,
+    scan: S,
+    rejected: bool,
+    invalid: Option<char>,
+    non_ascii: Option<char>,
+}
+
+///
+/// How far an escaper got through a sequence that is checked across writes,
+/// like the `</script` that would end a script early, or the `]]>` that ends a CDATA section.
+///
+/// Every escaper has its own by default. Lend the same one to several escapers with
+/// `Escaper::with_scan()` to check content that is written through all of them.
+///
+/// ```
+/// use std::fmt::Write;
+/// use tagu::tools::{escaper, Context, Scan};
+/// let mut scan = Scan::default();
+/// let mut s = String::new();
+/// let mut w = escaper(&mut s, Context::HtmlRawText("script")).with_scan(&mut scan);
+/// assert!(write!(w, "</scr").is_ok());
+/// let mut w = escaper(&mut s, Context::HtmlRawText("script")).with_scan(&mut scan);
+/// assert!(write!(w, "ipt>").is_err());
+/// ```
+///
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Scan {
+    state: usize,
+    /// Right after the start tag of an html `pre`, `textarea` or `listing`,
+    /// where parsers drop a leading newline.
+    pub(crate) lead: bool,
+}
+
+///
+/// Writer adaptor that escapes according to a `Context`.
+///
+pub fn escaper<T: std::fmt::Write>(a: T, context: Context) -> Escaper<T> {
+    Escaper::new(a, context)
+}
+
+impl<T: std::fmt::Write> Escaper<T> {
+    pub fn new(writer: T, context: Context) -> Escaper<T> {
+        Escaper {
+            writer,
+            context,
+            invalid_chars: InvalidChars::Replace,
+            escaping: Escaping::Full,
+            ascii: false,
+            scan: Scan::default(),
+            rejected: false,
+            invalid: None,
+            non_ascii: None,
+        }
+    }
+}
+
+impl<T: std::fmt::Write, S: BorrowMut<Scan>> Escaper<T, S> {
+    ///
+    /// Carry on from where an earlier escaper left off, and leave the progress in `scan`.
+    ///
+    pub fn with_scan<B: BorrowMut<Scan>>(self, scan: B) -> Escaper<T, B> {
+        Escaper {
+            writer: self.writer,
+            context: self.context,
+            invalid_chars: self.invalid_chars,
+            escaping: self.escaping,
+            ascii: self.ascii,
+            scan,
+            rejected: self.rejected,
+            invalid: self.invalid,
+            non_ascii: self.non_ascii,
+        }
+    }
+
+    fn state(&mut self) -> &mut usize {
+        &mut self.scan.borrow_mut().state
+    }
+
+    ///
+    /// Set what happens to characters xml does not allow.
+    ///
+    pub fn with_invalid_chars(mut self, invalid_chars: InvalidChars) -> Self {
+        self.invalid_chars = invalid_chars;
+        self
+    }
+
+    ///
+    /// Set which characters are escaped.
+    ///
+    pub fn with_escaping(mut self, escaping: Escaping) -> Self {
+        self.escaping = escaping;
+        self
+    }
+
+    ///
+    /// Write every non-ascii character as a numeric character reference.
+    ///
+    /// Comments, processing instructions and html raw text are not decoded by parsers,
+    /// so non-ascii characters there fail with `ErrorKind::NonAscii`. CDATA sections are split around the reference.
+    ///
+    pub fn with_ascii(mut self, ascii: bool) -> Self {
+        self.ascii = ascii;
+        self
+    }
+
+    ///
+    /// The error to report after a write failed.
+    ///
+    pub(crate) fn error(&self) -> crate::Error {
+        if let Some(c) = self.invalid {
+            crate::Error::new(crate::ErrorKind::InvalidChar(c))
+        } else if let Some(c) = self.non_ascii {
+            crate::Error::new(crate::ErrorKind::NonAscii(c))
+        } else if self.rejected {
+            crate::Error::new(crate::ErrorKind::Escape)
+        } else {
+            crate::Error::new(crate::ErrorKind::Fmt)
+        }
+    }
+
+    ///
+    /// Check that the content written so far is complete. Only comments can fail this,
+    /// if they end with `-`.
+    ///
+    pub fn finish(&self) -> fmt::Result {
+        match (self.context, self.scan.borrow().state) {
+            (Context::Comment, 2 | 3) => Err(fmt::Error),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_raw_text(&mut self, tag: &str, s: &str) -> fmt::Result {
+        let invalid_chars = self.invalid_chars;
+        for c in s.chars().filter(|&c| !invalid_chars.strips(c)) {
+            let state = self.state();
+            let expected = match *state {
+                0 => Some('<'),
+                1 => Some('/'),
+                n => tag[n - 2..].chars().next(),
+            };
+
+            *state = match expected {
+                Some(e) if e.eq_ignore_ascii_case(&c) => *state + 1,
+                _ if c == '<' => 1,
+                _ => 0,
+            };
+
+            if *state == tag.len() + 2 {
+                self.rejected = true;
+                return Err(fmt::Error);
+            }
+        }
+        Ok(())
+    }
+
+    // 1 after a question mark.
+    fn check_processing_instruction(&mut self, s: &str) -> fmt::Result {
+        let invalid_chars = self.invalid_chars;
+        for c in s.chars().filter(|&c| !invalid_chars.strips(c)) {
+            if *self.state() == 1 && c == '>' {
+                self.rejected = true;
+                return Err(fmt::Error);
+            }
+            *self.state() = usize::from(c == '?');
+        }
+        Ok(())
+    }
+
+    // 0 at the start, 1 after anything else, 2 after a dash, 3 after a leading dash.
+    fn check_comment(&mut self, s: &str) -> fmt::Result {
+        let invalid_chars = self.invalid_chars;
+        for c in s.chars().filter(|&c| !invalid_chars.strips(c)) {
+            *self.state() = match (*self.state(), c) {
+                (0, '>') | (3, '>' | '-') | (2, '-') => {
+                    self.rejected = true;
+                    return Err(fmt::Error);
+                }
+                (0, '-') => 3,
+                (_, '-') => 2,
+                _ => 1,
+            };
+        }
+        Ok(())
+    }
+}
+
+impl<T: std::fmt::Write, S: BorrowMut<Scan>> Escaper<T, S> {
+    ///
+    /// The reference to write instead of `c`, if any.
+    ///
+    fn escape(&mut self, c: char) -> Option<&'static str> {
+        let state = *self.state();
+        let r = match (self.context, self.escaping, c) {
+            (Context::Cdata, _, '>') if state >= 2 => Some("]]><![CDATA[>"),
+            (
+                Context::HtmlRawText(_)
+                | Context::Comment
+                | Context::Cdata
+                | Context::ProcessingInstruction,
+                _,
+                _,
+            ) => None,
+            (_, _, '&') => Some("&amp;"),
+            (Context::Attr, Escaping::MinimalHtml, '<') => None,
+            (_, _, '<') => Some("&lt;"),
+            (Context::Attr, _, '"') => Some("&quot;"),
+            (Context::Attr, _, '\n') => Some("&#10;"),
+            (Context::Attr, _, '\r') => Some("&#13;"),
+            (Context::Attr, _, '\t') => Some("&#9;"),
+            (Context::Text | Context::Attr, Escaping::Full, '>') => Some("&gt;"),
+            (Context::Text, Escaping::MinimalXml, '>') | (Context::XmlRawText, _, '>')
+                if state >= 2 =>
+            {
+                Some("&gt;")
+            }
+            _ => None,
+        };
+
+        // Count the `]` before a `>`.
+        if matches!(
+            self.context,
+            Context::Text | Context::XmlRawText | Context::Cdata
+        ) {
+            *self.state() = if c == ']' { state + 1 } else { 0 };
+        }
+        r
+    }
+}
+
+impl<T: std::fmt::Write, S: BorrowMut<Scan>> std::fmt::Write for Escaper<T, S> {
+    fn write_str(&mut self, s: &str) -> Result<(), std::fmt::Error> {
+        match self.context {
+            Context::HtmlRawText(tag) => self.check_raw_text(tag, s)?,
+            Context::Comment => self.check_comment(s)?,
+            Context::ProcessingInstruction => self.check_processing_instruction(s)?,
+            _ => {}
+        }
+
+        if !s.is_empty() && std::mem::take(&mut self.scan.borrow_mut().lead) {
+            // Make up for the newline that will be dropped.
+            if s.starts_with(['\n', '\r']) {
+                self.writer.write_char('\n')?;
+            }
+        }
+
+        // Write unchanged runs in one go.
+        let mut last = 0;
+        for (i, c) in s.char_indices() {
+            let out = if is_xml_char(c) {
+                c
+            } else {
+                match self.invalid_chars {
+                    InvalidChars::Replace => '\u{FFFD}',
+                    InvalidChars::Strip => {
+                        self.writer.write_str(&s[last..i])?;
+                        last = i + c.len_utf8();
+                        continue;
+                    }
+                    InvalidChars::Error => {
+                        self.invalid = Some(c);
+                        return Err(fmt::Error);
+                    }
+                }
+            };
+
+            let r = self.escape(out);
+            let encode = self.ascii && !out.is_ascii();
+            if r.is_none() && !encode && out == c {
+                continue;
+            }
+
+            self.writer.write_str(&s[last..i])?;
+            last = i + c.len_utf8();
+            match r {
+                Some(r) => self.writer.write_str(r)?,
+                None if encode => match self.context {
+                    Context::HtmlRawText(_) | Context::Comment | Context::ProcessingInstruction => {
+                        self.non_ascii = Some(out);
+                        return Err(fmt::Error);
+                    }
+                    // Step out of the section for the reference.
+                    Context::Cdata => {
+                        write!(self.writer, "]]>&#x{:X};<![CDATA[", out as u32)?;
+                    }
+                    _ => write!(self.writer, "&#x{:X};", out as u32)?,
+                },
+                None => self.writer.write_char(out)?,
+            }
+        }
+        self.writer.write_str(&s[last..])
+    }
+}
+
+///
+/// Whether `c` may start an xml `Name`.
+///
+const fn is_name_start_char(c: char) -> bool {
+    matches!(c,
+        ':' | 'A'..='Z' | '_' | 'a'..='z' | '\u{C0}'..='\u{D6}' | '\u{D8}'..='\u{F6}'
+        | '\u{F8}'..='\u{2FF}' | '\u{370}'..='\u{37D}' | '\u{37F}'..='\u{1FFF}'
+        | '\u{200C}'..='\u{200D}' | '\u{2070}'..='\u{218F}' | '\u{2C00}'..='\u{2FEF}'
+        | '\u{3001}'..='\u{D7FF}' | '\u{F900}'..='\u{FDCF}' | '\u{FDF0}'..='\u{FFFD}'
+        | '\u{10000}'..='\u{EFFFF}')
+}
+
+///
+/// Whether `c` may appear in an xml `Name` after the first character.
+///
+const fn is_name_char(c: char) -> bool {
+    is_name_start_char(c)
+        || matches!(c,
+            '-' | '.' | '0'..='9' | '\u{B7}' | '\u{300}'..='\u{36F}' | '\u{203F}'..='\u{2040}')
+}
+
+///
+/// Check a name against the xml `Name` production.
+///
+/// ```
+/// use tagu::tools::is_name;
+/// assert!(is_name("xlink:href"));
+/// assert!(is_name("données"));
+/// assert!(!is_name("on click"));
+/// assert!(!is_name("1st"));
+/// assert!(!is_name(""));
+/// ```
+///
+pub fn is_name(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars.next().is_some_and(is_name_start_char) && chars.all(is_name_char)
+}
+
+///
+/// Check a tag name. In html, it also has to start with an ascii letter.
+///
+/// Attribute names are only checked against the xml `Name` production, which
+/// already leaves out everything html does not allow in them, like whitespace, quotes, `/`, `=` and `>`.
+///
+pub(crate) fn is_tag_name(s: &str, html: bool) -> bool {
+    is_name(s) && (!html || s.starts_with(|c: char| c.is_ascii_alphabetic()))
+}
+
+///
+/// Decode the char that starts at byte `i` of valid utf-8, and its length.
+///
+const fn decode_char(b: &[u8], i: usize) -> (char, usize) {
+    let first = b[i] as u32;
+    let (mut c, n) = match first {
+        0..=0x7F => (first, 1),
+        0x80..=0xDF => (first & 0x1F, 2),
+        0xE0..=0xEF => (first & 0x0F, 3),
+        _ => (first & 0x07, 4),
+    };
+    let mut k = 1;
+    while k < n {
+        c = (c << 6) | (b[i + k] as u32 & 0x3F);
+        k += 1;
+    }
+    match char::from_u32(c) {
+        Some(c) => (c, n),
+        None => ('\u{FFFD}', n),
+    }
+}
+
+///
+/// A tag or attribute name that is checked once when it is made,
+/// instead of every time it is written.
+///
+/// Any `Display` can be used as a name, but is checked char by char on every render.
+///
+/// ```
+/// use tagu::build;
+/// use tagu::tools::Name;
+/// const ROW: Name = Name::new("row");
+/// const SPAN: Name = Name::new("data-span");
+/// let k = build::single(ROW).with((SPAN, 2));
+/// assert_eq!(tagu::render_to_string(k).unwrap(), "<row data-span=\"2\"/>\n");
+///
+/// assert!(Name::try_new("1st").is_none());
+///
+/// // What depends on the mode is still checked when rendering.
+/// let k = build::single(Name::new("_row"));
+/// assert!(tagu::render_html(k, &mut String::new()).is_err());
+/// ```
+///
+/// In a const, an invalid name fails to compile.
+///
+/// ```compile_fail
+/// const ROW: tagu::tools::Name = tagu::tools::Name::new("my row");
+/// ```
+///
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Name {
+    name: &'static str,
+    ascii: bool,
+}
+
+impl Name {
+    ///
+    /// Panics if `name` is not an xml `Name`.
+    ///
+    pub const fn new(name: &'static str) -> Name {
+        match Name::try_new(name) {
+            Some(a) => a,
+            None => panic!("not an xml Name"),
+        }
+    }
+
+    ///
+    /// `None` if `name` is not an xml `Name`.
+    ///
+    pub const fn try_new(name: &'static str) -> Option<Name> {
+        let b = name.as_bytes();
+        let mut i = 0;
+        while i < b.len() {
+            let (c, n) = decode_char(b, i);
+            let ok = if i == 0 {
+                is_name_start_char(c)
+            } else {
+                is_name_char(c)
+            };
+            if !ok {
+                return None;
+            }
+            i += n;
+        }
+        if b.is_empty() {
+            return None;
+        }
+        Some(Name {
+            name,
+            ascii: name.is_ascii(),
+        })
+    }
+
+    pub const fn as_str(&self) -> &'static str {
+        self.name
+    }
+
+    pub(crate) fn is_ascii(&self) -> bool {
+        self.ascii
+    }
+}
+
+///
+/// Something that can be written as a tag or attribute name.
+///
+/// Implemented for every `Display`, which is checked as it is written, and for `Name`,
+/// which was checked already.
+///
+pub trait AsName {
+    ///
+    /// Write the name as is.
+    ///
+    fn write_name(&self, w: &mut dyn fmt::Write) -> fmt::Result;
+
+    ///
+    /// The name, if it is known to be valid.
+    ///
+    fn checked(&self) -> Option<Name> {
+        None
+    }
+}
+
+impl<T: fmt::Display + ?Sized> AsName for T {
+    fn write_name(&self, w: &mut dyn fmt::Write) -> fmt::Result {
+        write!(w, "{}", self)
+    }
+}
+
+impl AsName for Name {
+    fn write_name(&self, w: &mut dyn fmt::Write) -> fmt::Result {
+        w.write_str(self.name)
+    }
+    fn checked(&self) -> Option<Name> {
+        Some(*self)
+    }
+}
+
+///
+/// Displays a name as it is written.
+///
+pub(crate) struct ShowName<'a, T: ?Sized>(pub(crate) &'a T);
+
+impl<T: AsName + ?Sized> fmt::Display for ShowName<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.write_name(f)
+    }
+}
+
+///
+/// Writer adaptor that checks that what is written is an xml `Name`, instead of escaping it.
+///
+pub(crate) struct NameWriter<T> {
+    writer: T,
+    ascii: bool,
+    empty: bool,
+    rejected: bool,
+}
+
+pub(crate) fn name_writer<T: fmt::Write>(writer: T, ascii: bool) -> NameWriter<T> {
+    NameWriter {
+        writer,
+        ascii,
+        empty: true,
+        rejected: false,
+    }
+}
+
+impl<T: fmt::Write> NameWriter<T> {
+    ///
+    /// Check that something was written.
+    ///
+    pub(crate) fn finish(&mut self) -> fmt::Result {
+        if self.empty {
+            self.rejected = true;
+            return Err(fmt::Error);
+        }
+        Ok(())
+    }
+
+    ///
+    /// The error to report after a write failed, given the name that was written.
+    ///
+    pub(crate) fn error(&self, name: impl FnOnce() -> String) -> crate::Error {
+        if self.rejected {
+            crate::Error::new(crate::ErrorKind::Name(name()))
+        } else {
+            crate::Error::new(crate::ErrorKind::Fmt)
+        }
+    }
+}
+
+impl<T: fmt::Write> fmt::Write for NameWriter<T> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            let ok = if self.empty {
+                is_name_start_char(c)
+            } else {
+                is_name_char(c)
+            };
+            if !ok || (self.ascii && !c.is_ascii()) {
+                self.rejected = true;
+                return Err(fmt::Error);
+            }
+            self.empty = false;
+        }
+        self.writer.write_str(s)
+    }
+}
+
+///
+/// Check a css property name, which is an identifier or a custom property like `--main-color`.
+///
+pub(crate) fn is_css_property(s: &str) -> bool {
+    let body = s
+        .strip_prefix("--")
+        .unwrap_or(s.strip_prefix('-').unwrap_or(s));
+    let ident = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_' || !c.is_ascii();
+    !body.is_empty()
+        && (s.starts_with("--") || !body.starts_with(|c: char| c.is_ascii_digit() || c == '-'))
+        && body.chars().all(ident)
+}
+
+///
+/// Escape a css value so that it can't end its declaration. Outside of strings,
+/// `;`, `{`, `}` and the start of a comment are escaped, and line breaks are escaped
+/// everywhere. A string that is still open at the end is closed. Css escapes like `\f101`
+/// are kept, except for a backslash that would escape a line break or the end of the value.
+///
+pub(crate) fn css_value(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut quote = None;
+    let mut last = None;
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.peek() {
+                Some(&a) if !matches!(a, '\n' | '\r' | '\u{C}') => {
+                    out.push(c);
+                    out.push(a);
+                    chars.next();
+                    // An escaped `/` can't start a comment.
+                    last = None;
+                    continue;
+                }
+                _ => out.push_str("\\\\"),
+            },
+            '\n' => out.push_str("\\A "),
+            '\r' => out.push_str("\\D "),
+            '\u{C}' => out.push_str("\\C "),
+            '"' | '\'' if quote == Some(c) => {
+                quote = None;
+                out.push(c);
+            }
+            '"' | '\'' if quote.is_none() => {
+                quote = Some(c);
+                out.push(c);
+            }
+            ';' | '{' | '}' if quote.is_none() => {
+                out.push('\\');
+                out.push(c);
+            }
+            '*' if quote.is_none() && last == Some('/') => out.push_str("\\*"),
+            c => out.push(c),
+        }
+        last = Some(c);
+    }
+    out.extend(quote);
+    out
+}
+
+///
+/// Split css declarations on the `;` between them, leaving alone those in strings,
+/// in parentheses like `url(a;b)` and those that are escaped.
+///
+pub(crate) fn css_declarations(s: &str) -> impl Iterator<Item = &str> {
+    let mut quote = None;
+    let mut depth = 0usize;
+    let mut escaped = false;
+    s.split(move |c: char| {
+        if escaped {
+            escaped = false;
+            return false;
+        }
+        match c {
+            '\\' => escaped = true,
+            '"' | '\'' if quote == Some(c) => quote = None,
+            _ if quote.is_some() => {}
+            '"' | '\'' => quote = Some(c),
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ';' => return depth == 0,
+            _ => {}
+        }
+        false
+    })
+}
+
+///
+/// The lines of a block of text, without the blank lines around it,
+/// and without the leading whitespace that all of its lines share.
+/// Blank lines inside the block are made empty.
+///
+pub(crate) fn dedent(s: &str) -> Vec<&str> {
+    let blank = |a: &&str| a.trim().is_empty();
+    let mut lines: Vec<&str> = s.lines().skip_while(blank).collect();
+    while lines.last().is_some_and(blank) {
+        lines.pop();
+    }
+
+    let indent = lines
+        .iter()
+        .filter(|a| !blank(a))
+        .map(|a| &a[..a.len() - a.trim_start().len()])
+        .reduce(|a, b| {
+            let end = a
+                .char_indices()
+                .zip(b.chars())
+                .find(|((_, x), y)| x != y)
+                .map_or(a.len().min(b.len()), |((i, _), _)| i);
+            &a[..end]
+        })
+        .unwrap_or("");
+
+    for a in lines.iter_mut() {
+        *a = if blank(a) { "" } else { &a[indent.len()..] };
+    }
+    lines
+}
+
 ///
 /// Used to wrap a `std::io::Write` to have `std::fmt::Write`.
 /// The underlying error can be extracted through the error field.
@@ -115,13 +922,105 @@ impl<F: Fn(&mut fmt::Formatter) -> fmt::Result> fmt::Display for DisplayableClos
     }
 }
 
+///
+/// The writer that elements are rendered to.
+///
+/// Unlike a plain `fmt::Write`, it is told whenever an element has been completely written,
+/// and when the output so far should be pushed to its destination.
+///
+/// ```
+/// use std::fmt;
+/// use tagu::build;
+/// use tagu::prelude::*;
+/// use tagu::tools::Sink;
+///
+/// struct Parts(Vec<String>);
+/// impl fmt::Write for Parts {
+///     fn write_str(&mut self, s: &str) -> fmt::Result {
+///         self.0.last_mut().unwrap().push_str(s);
+///         Ok(())
+///     }
+/// }
+/// impl Sink for Parts {
+///     fn flush(&mut self) -> fmt::Result {
+///         self.0.push(String::new());
+///         Ok(())
+///     }
+/// }
+///
+/// let all = build::from_stack(|mut stack| {
+///     stack.put(build::elem("head").inline())?;
+///     stack.flush()?;
+///     stack.put(build::elem("body").inline())?;
+///     Ok(stack)
+/// });
+/// let mut parts = Parts(vec![String::new()]);
+/// tagu::render::Renderer::new().render_sink(all, &mut parts).unwrap();
+/// assert_eq!(parts.0, ["<head></head>\n", "<body></body>\n"]);
+/// ```
+///
+pub trait Sink: fmt::Write {
+    ///
+    /// Called after an element has been completely written.
+    ///
+    fn boundary(&mut self) -> fmt::Result {
+        Ok(())
+    }
+
+    ///
+    /// Push out everything written so far.
+    ///
+    fn flush(&mut self) -> fmt::Result {
+        Ok(())
+    }
+}
+
+impl<S: Sink + ?Sized> Sink for &mut S {
+    fn boundary(&mut self) -> fmt::Result {
+        (**self).boundary()
+    }
+    fn flush(&mut self) -> fmt::Result {
+        (**self).flush()
+    }
+}
+
+impl<T: std::io::Write> Sink for Adaptor<T> {
+    fn flush(&mut self) -> fmt::Result {
+        match self.inner.flush() {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                self.error = Err(e);
+                Err(fmt::Error)
+            }
+        }
+    }
+}
+
+///
+/// A `fmt::Write` that does not care about element boundaries.
+///
+pub(crate) struct Plain<W>(pub(crate) W);
+
+impl<W: fmt::Write> fmt::Write for Plain<W> {
+    fn write_str(&mut self, s: &str) -> Result<(), fmt::Error> {
+        self.0.write_str(s)
+    }
+    fn write_char(&mut self, c: char) -> Result<(), fmt::Error> {
+        self.0.write_char(c)
+    }
+    fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> Result<(), fmt::Error> {
+        self.0.write_fmt(args)
+    }
+}
+impl<W: fmt::Write> Sink for Plain<W> {}
+
 ///
 /// Wrapper around a &mut dyn std::fmt::Write
 ///
 pub struct WriteWrap<'a>(pub &'a mut dyn fmt::Write);
 
 impl<'a> WriteWrap<'a> {
-    pub fn borrow_mut(&mut self) -> WriteWrap {
+    pub fn borrow_mut(&mut self) -> WriteWrap<'_> {
         WriteWrap(self.0)
     }
 }
@@ -137,3 +1036,38 @@ impl fmt::Write for WriteWrap<'_> {
         self.0.write_fmt(args)
     }
 }
+
+///
+/// Wrapper around the sink elements are rendered to.
+///
+pub(crate) struct SinkWrap<'a>(pub(crate) &'a mut dyn Sink);
+
+impl<'a> SinkWrap<'a> {
+    pub(crate) fn borrow_mut(&mut self) -> SinkWrap<'_> {
+        SinkWrap(self.0)
+    }
+    ///
+    /// The sink as a plain writer.
+    ///
+    pub(crate) fn as_write(&mut self) -> WriteWrap<'_> {
+        WriteWrap(self)
+    }
+    pub(crate) fn boundary(&mut self) -> fmt::Result {
+        self.0.boundary()
+    }
+    pub(crate) fn flush(&mut self) -> fmt::Result {
+        self.0.flush()
+    }
+}
+impl fmt::Write for SinkWrap<'_> {
+    fn write_str(&mut self, s: &str) -> Result<(), fmt::Error> {
+        self.0.write_str(s)
+    }
+
+    fn write_char(&mut self, c: char) -> Result<(), fmt::Error> {
+        self.0.write_char(c)
+    }
+    fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> Result<(), fmt::Error> {
+        self.0.write_fmt(args)
+    }
+}